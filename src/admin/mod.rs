@@ -0,0 +1,48 @@
+use actix_web::error::{ErrorForbidden, ErrorUnauthorized};
+use actix_web::{Error, HttpRequest};
+use dotenv::dotenv;
+use sea_orm::{ActiveModelBehavior, ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, TransactionTrait};
+use std::env;
+
+pub mod regeocode;
+pub mod rescore;
+
+/// Admin endpoints are only enabled if `ADMIN_TOKEN` is set and have to be called
+/// with the same value in the `X-Admin-Token` header.
+pub fn check_admin_token(http_request: &HttpRequest) -> Result<(), Error> {
+    dotenv().ok();
+
+    let admin_token = match env::var("ADMIN_TOKEN") {
+        Ok(admin_token) if !admin_token.is_empty() => admin_token,
+        _ => return Err(ErrorForbidden("Admin endpoints are disabled!"))
+    };
+
+    match http_request.headers().get("X-Admin-Token") {
+        Some(token) if token.as_bytes() == admin_token.as_bytes() => Ok(()),
+        Some(_) => Err(ErrorForbidden("Invalid `X-Admin-Token` header!")),
+        None => Err(ErrorUnauthorized("Missing `X-Admin-Token` header!"))
+    }
+}
+
+/// Updates a batch of partially set active models in a single transaction.
+pub async fn update_in_transaction<A>(active_models: Vec<A>, db: &DatabaseConnection) -> Result<(), DbErr>
+where
+    A: ActiveModelTrait + ActiveModelBehavior + Send + 'static,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>
+{
+    if active_models.is_empty() {
+        return Ok(());
+    }
+
+    db.transaction::<_, _, DbErr>(|txn| {
+        Box::pin(async move {
+            for active_model in active_models {
+                active_model.update(txn).await?;
+            }
+
+            Ok(())
+        })
+    })
+        .await
+        .map_err(|err| DbErr::Custom(err.to_string()))
+}
@@ -0,0 +1,255 @@
+use crate::admin::{check_admin_token, update_in_transaction};
+use crate::entities::geocoding_run::ActiveModel as GeocodingRunModel;
+use crate::entities::br_guess::ActiveModel as BrGuessModel;
+use crate::entities::guess::ActiveModel as GuessModel;
+use crate::entities::location::ActiveModel as LocationModel;
+use crate::entities::prelude::{BrGuess, GeocodingRun, Guess, Location};
+use crate::entities::{br_guess, geocoding_run, guess, location};
+use crate::requests::geocoder::geocoder;
+use actix_web::error::{ErrorConflict, ErrorInternalServerError};
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use lazy_static::lazy_static;
+use log::{error, info};
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const BATCH_SIZE: u64 = 1000;
+
+lazy_static! {
+    static ref REGEOCODE_LOCK: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+}
+
+/// Runs the geocoding of a batch on the blocking thread pool, probing for borders is CPU heavy.
+async fn geocode_batch<T, R, F>(items: Vec<T>, geocode: F) -> Result<Vec<R>, DbErr>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> Option<R> + Send + 'static
+{
+    tokio::task::spawn_blocking(move || items.into_iter().filter_map(geocode).collect())
+        .await
+        .map_err(|err| DbErr::Custom(err.to_string()))
+}
+
+/// Returns how many guesses were checked and how many of them got new codes.
+async fn regeocode_guesses(db: &DatabaseConnection) -> Result<(i32, i32), DbErr> {
+    let mut checked = 0;
+    let mut changed = 0;
+    let mut last_id = None;
+
+    loop {
+        let mut query = Guess::find();
+
+        if let Some(last_id) = last_id.take() {
+            query = query.filter(guess::Column::Id.gt(last_id));
+        }
+
+        let guesses = query
+            .order_by_asc(guess::Column::Id)
+            .limit(BATCH_SIZE)
+            .all(db)
+            .await?;
+
+        let Some(last) = guesses.last() else {
+            break;
+        };
+
+        last_id = Some(last.id.clone());
+        checked += guesses.len() as i32;
+
+        let changed_guesses = geocode_batch(guesses, |guess| {
+            let geocode = geocoder().geocode(guess.lat, guess.lng);
+
+            (geocode.country_code != guess.country_code
+                || geocode.subdivision_code != guess.subdivision_code
+                || geocode.border_distance != guess.border_distance)
+                .then(|| GuessModel {
+                    id: ActiveValue::Unchanged(guess.id),
+                    country_code: ActiveValue::Set(geocode.country_code),
+                    subdivision_code: ActiveValue::Set(geocode.subdivision_code),
+                    border_distance: ActiveValue::Set(geocode.border_distance),
+                    ..Default::default()
+                })
+        }).await?;
+
+        changed += changed_guesses.len() as i32;
+        update_in_transaction(changed_guesses, db).await?;
+    }
+
+    Ok((checked, changed))
+}
+
+/// Battle royale guesses do not store a border distance, only their codes are compared.
+async fn regeocode_br_guesses(db: &DatabaseConnection) -> Result<(i32, i32), DbErr> {
+    let mut checked = 0;
+    let mut changed = 0;
+    let mut last_id = None;
+
+    loop {
+        let mut query = BrGuess::find();
+
+        if let Some(last_id) = last_id.take() {
+            query = query.filter(br_guess::Column::Id.gt(last_id));
+        }
+
+        let guesses = query
+            .order_by_asc(br_guess::Column::Id)
+            .limit(BATCH_SIZE)
+            .all(db)
+            .await?;
+
+        let Some(last) = guesses.last() else {
+            break;
+        };
+
+        last_id = Some(last.id.clone());
+        checked += guesses.len() as i32;
+
+        let changed_guesses = geocode_batch(guesses, |guess| {
+            let geocode = geocoder().geocode(guess.lat, guess.lng);
+
+            (geocode.country_code != guess.country_code || geocode.subdivision_code != guess.subdivision_code)
+                .then(|| BrGuessModel {
+                    id: ActiveValue::Unchanged(guess.id),
+                    country_code: ActiveValue::Set(geocode.country_code),
+                    subdivision_code: ActiveValue::Set(geocode.subdivision_code),
+                    ..Default::default()
+                })
+        }).await?;
+
+        changed += changed_guesses.len() as i32;
+        update_in_transaction(changed_guesses, db).await?;
+    }
+
+    Ok((checked, changed))
+}
+
+/// Only the subdivision is geocoded for locations, the country code comes from GeoGuessr.
+async fn regeocode_locations(db: &DatabaseConnection) -> Result<(i32, i32), DbErr> {
+    let mut checked = 0;
+    let mut changed = 0;
+    let mut last_id = None;
+
+    loop {
+        let mut query = Location::find();
+
+        if let Some(last_id) = last_id.take() {
+            query = query.filter(location::Column::Id.gt(last_id));
+        }
+
+        let locations = query
+            .order_by_asc(location::Column::Id)
+            .limit(BATCH_SIZE)
+            .all(db)
+            .await?;
+
+        let Some(last) = locations.last() else {
+            break;
+        };
+
+        last_id = Some(last.id.clone());
+        checked += locations.len() as i32;
+
+        let changed_locations = geocode_batch(locations, |location| {
+            let subdivision_code = geocoder().subdivision_code(location.lat, location.lng);
+
+            (subdivision_code != location.subdivision_code).then(|| LocationModel {
+                id: ActiveValue::Unchanged(location.id),
+                subdivision_code: ActiveValue::Set(subdivision_code),
+                ..Default::default()
+            })
+        }).await?;
+
+        changed += changed_locations.len() as i32;
+        update_in_transaction(changed_locations, db).await?;
+    }
+
+    Ok((checked, changed))
+}
+
+/// Re-geocodes all guesses and locations and records the run once it is done.
+/// Battle royale guesses are counted with the other guesses.
+async fn run_regeocode(db: &DatabaseConnection) -> Result<geocoding_run::Model, DbErr> {
+    let start_time = Utc::now();
+
+    let (guesses_checked, guesses_changed) = regeocode_guesses(db).await?;
+    let (br_guesses_checked, br_guesses_changed) = regeocode_br_guesses(db).await?;
+    let (locations_checked, locations_changed) = regeocode_locations(db).await?;
+
+    let geocoding_run = GeocodingRunModel {
+        id: ActiveValue::Set(Uuid::new_v4().to_string()),
+        dataset_version: ActiveValue::Set(String::from(geocoder().dataset_version())),
+        start_time: ActiveValue::Set(start_time.to_string()),
+        end_time: ActiveValue::Set(Utc::now().to_string()),
+        guesses_checked: ActiveValue::Set(guesses_checked + br_guesses_checked),
+        guesses_changed: ActiveValue::Set(guesses_changed + br_guesses_changed),
+        locations_checked: ActiveValue::Set(locations_checked),
+        locations_changed: ActiveValue::Set(locations_changed)
+    };
+
+    GeocodingRun::insert(geocoding_run).exec_with_returning(db).await
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RegeocodeStatus {
+    running: bool,
+    last_run: Option<geocoding_run::Model>
+}
+
+/// Returns whether re-geocoding is running and the counts of the latest finished run.
+#[get("/admin/regeocode")]
+async fn get_regeocode_status(
+    db: web::Data<DatabaseConnection>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    check_admin_token(&http_request)?;
+
+    let last_run = GeocodingRun::find()
+        .order_by_desc(geocoding_run::Column::StartTime)
+        .one(db.get_ref())
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let response = RegeocodeStatus {
+        running: REGEOCODE_LOCK.try_lock().is_err(),
+        last_run
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Starts re-geocoding in the background, the result is stored as a geocoding run
+/// and returned by `GET /admin/regeocode`.
+#[post("/admin/regeocode")]
+async fn regeocode(
+    db: web::Data<DatabaseConnection>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    check_admin_token(&http_request)?;
+
+    let guard = REGEOCODE_LOCK
+        .clone()
+        .try_lock_owned()
+        .map_err(|_| ErrorConflict("Re-geocoding is already running!"))?;
+
+    let db = db.get_ref().clone();
+
+    tokio::spawn(async move {
+        let _guard = guard;
+
+        match run_regeocode(&db).await {
+            Ok(geocoding_run) => info!(
+                "Re-geocoding with dataset {} changed {} guesses and {} locations",
+                geocoding_run.dataset_version, geocoding_run.guesses_changed, geocoding_run.locations_changed
+            ),
+            Err(err) => error!("Could not re-geocode! Error: {}", err)
+        }
+    });
+
+    Ok(HttpResponse::Accepted().finish())
+}
@@ -0,0 +1,299 @@
+use crate::admin::{check_admin_token, update_in_transaction};
+use crate::entities::duels_game::ActiveModel as DuelsGameModel;
+use crate::entities::guess::ActiveModel as GuessModel;
+use crate::geo_guessr::TeamGameMode;
+use crate::entities::prelude::{DuelsGame, DuelsRound, Guess, Map};
+use crate::entities::{duels_game, duels_round, guess};
+use crate::requests::insertion_requests::fetch_duels_game;
+use crate::requests::scoring::get_score;
+use crate::requests::transform::get_team_ratings;
+use actix_web::error::ErrorConflict;
+use actix_web::{post, web, Error, HttpRequest, HttpResponse, Responder};
+use futures::future::join_all;
+use lazy_static::lazy_static;
+use log::{error, info};
+use reqwest::Client;
+use sea_orm::{ActiveValue, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+const GAME_BATCH_SIZE: u64 = 50;
+
+lazy_static! {
+    static ref RESCORE_LOCK: Arc<Mutex<()>> = Arc::new(Mutex::new(()));
+}
+
+#[derive(Default)]
+struct RescoreSummary {
+    games_checked: usize,
+    games_fetched: usize,
+    games_failed: usize,
+    games_updated: usize,
+    guesses_derived: usize,
+    guesses_official: usize,
+    guesses_attributed: usize,
+    guesses_updated: usize
+}
+
+/// Round number, latitude and longitude of a guess, enough to match stored guesses to GeoGuessr's.
+type GuessKey = (i32, u64, u64);
+
+struct OfficialGuess {
+    score: Option<i32>,
+    player_id: String
+}
+
+struct OfficialScores {
+    max_error_distance: f64,
+    guesses: HashMap<GuessKey, OfficialGuess>
+}
+
+fn get_official_scores(game: &crate::geo_guessr::DuelsGame) -> OfficialScores {
+    let guesses = game
+        .teams
+        .iter()
+        .flat_map(|team| team.players.iter())
+        .flat_map(|player| player.guesses.iter().map(move |guess| (player, guess)))
+        .map(|(player, guess)| (
+            (guess.round_number - 1, guess.lat.to_bits(), guess.lng.to_bits()),
+            OfficialGuess {
+                score: guess.score,
+                player_id: player.player_id.clone()
+            }
+        ))
+        .collect();
+
+    OfficialScores {
+        max_error_distance: game.options.map.max_error_distance as f64,
+        guesses
+    }
+}
+
+/// Games stored before ratings were read from the right team have their ratings swapped and no
+/// ratings after the game, returns the corrected ratings if they differ from the stored ones.
+fn get_changed_ratings(stored_game: &duels_game::Model, game: &crate::geo_guessr::DuelsGame) -> Option<DuelsGameModel> {
+    let game_mode = TeamGameMode::from_str(&stored_game.team_game_mode).ok()?;
+
+    if game.teams.len() != 2 || game.teams.iter().any(|team| team.players.is_empty()) {
+        return None;
+    }
+
+    let ratings_team1 = get_team_ratings(&game.teams[0], &game_mode);
+    let ratings_team2 = get_team_ratings(&game.teams[1], &game_mode);
+
+    if (stored_game.rating_before_team1, stored_game.rating_after_team1) == ratings_team1
+        && (stored_game.rating_before_team2, stored_game.rating_after_team2) == ratings_team2
+    {
+        return None;
+    }
+
+    Some(DuelsGameModel {
+        id: ActiveValue::Unchanged(stored_game.id.clone()),
+        rating_before_team1: ActiveValue::Set(ratings_team1.0),
+        rating_before_team2: ActiveValue::Set(ratings_team2.0),
+        rating_after_team1: ActiveValue::Set(ratings_team1.1),
+        rating_after_team2: ActiveValue::Set(ratings_team2.1),
+        ..Default::default()
+    })
+}
+
+/// Rated games stored before ratings after the game were recorded. Their ratings before the
+/// game were also read from the opposite team. Unrated games never have ratings.
+fn is_missing_ratings(game: &duels_game::Model) -> bool {
+    game.rating_after_team1.is_none()
+        && matches!(
+            TeamGameMode::from_str(&game.team_game_mode),
+            Ok(TeamGameMode::DuelsRanked) | Ok(TeamGameMode::TeamDuelsRanked)
+        )
+}
+
+/// Rescores all stored duels games. Games with guesses of unknown provenance, stored before it
+/// was recorded whether their score was derived, are re-fetched from GeoGuessr to find out which
+/// of their scores were reported and who made the guesses. Rated games without ratings after the
+/// game are re-fetched as well to backfill their ratings. For all other games the derived scores are recomputed from the map.
+async fn run_rescore(db: &DatabaseConnection) -> Result<RescoreSummary, DbErr> {
+    let client = Client::new();
+    let mut summary = RescoreSummary::default();
+    let mut last_id = None;
+
+    let max_error_distances: HashMap<String, f64> = Map::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|map| (map.id, map.max_distance as f64))
+        .collect();
+
+    loop {
+        let mut query = DuelsGame::find();
+
+        if let Some(last_id) = last_id.take() {
+            query = query.filter(duels_game::Column::Id.gt(last_id));
+        }
+
+        let games = query
+            .order_by_asc(duels_game::Column::Id)
+            .limit(GAME_BATCH_SIZE)
+            .all(db)
+            .await?;
+
+        let Some(last) = games.last() else {
+            break;
+        };
+
+        last_id = Some(last.id.clone());
+
+        let game_ids: Vec<String> = games.iter().map(|game| game.id.clone()).collect();
+        let stored_games: HashMap<&String, &duels_game::Model> = games.iter().map(|game| (&game.id, game)).collect();
+
+        let guesses = Guess::find()
+            .filter(guess::Column::GameId.is_in(&game_ids))
+            .all(db)
+            .await?;
+
+        let unknown_game_ids: HashSet<&String> = guesses
+            .iter()
+            .filter(|guess| guess.is_score_derived.is_none())
+            .map(|guess| &guess.game_id)
+            .collect();
+
+        let fetched_game_ids: Vec<&String> = games
+            .iter()
+            .filter(|game| unknown_game_ids.contains(&game.id) || is_missing_ratings(game))
+            .map(|game| &game.id)
+            .collect();
+        let results = join_all(fetched_game_ids.iter().map(|game_id| fetch_duels_game(game_id, &client))).await;
+        let mut official_scores = HashMap::new();
+        let mut changed_games = Vec::new();
+
+        for (game_id, result) in fetched_game_ids.into_iter().zip(results) {
+            match result {
+                Ok(game) => {
+                    if let Some(changed_game) = get_changed_ratings(stored_games[game_id], &game) {
+                        changed_games.push(changed_game);
+                    }
+
+                    official_scores.insert(game_id.clone(), get_official_scores(&game));
+                    summary.games_fetched += 1;
+                }
+                Err(err) => {
+                    error!("Could not fetch game {} for rescoring! Error: {}", game_id, err);
+                    summary.games_failed += 1;
+                }
+            }
+        }
+
+        summary.games_checked += game_ids.len();
+
+        let round_numbers: HashMap<String, i32> = DuelsRound::find()
+            .filter(duels_round::Column::GameId.is_in(&game_ids))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|round| (round.id, round.round_number))
+            .collect();
+
+        let mut changed_guesses = Vec::new();
+
+        for guess in guesses {
+            let official_guess = official_scores
+                .get(&guess.game_id)
+                .zip(round_numbers.get(&guess.round_id))
+                .and_then(|(official, round_number)| {
+                    let key = (*round_number, guess.lat.to_bits(), guess.lng.to_bits());
+                    official.guesses.get(&key).map(|official_guess| (official, official_guess))
+                });
+
+            let (score, is_score_derived, player_id) = match official_guess {
+                Some((official, official_guess)) => {
+                    let (score, is_score_derived) = match official_guess.score {
+                        Some(score) => (score, false),
+                        None => (get_score(guess.distance, official.max_error_distance), true)
+                    };
+
+                    let player_id = if guess.player_id.is_none() {
+                        summary.guesses_attributed += 1;
+                        ActiveValue::Set(Some(official_guess.player_id.clone()))
+                    } else {
+                        ActiveValue::NotSet
+                    };
+
+                    (score, is_score_derived, player_id)
+                }
+                None => {
+                    match guess.is_score_derived {
+                        Some(false) => {
+                            summary.guesses_official += 1;
+                            continue;
+                        }
+                        // Unknown provenance, the game could not be fetched.
+                        None => continue,
+                        Some(true) => {}
+                    }
+
+                    let Some(max_error_distance) = stored_games.get(&guess.game_id).and_then(|game| max_error_distances.get(&game.map_id)) else {
+                        continue;
+                    };
+
+                    (get_score(guess.distance, *max_error_distance), true, ActiveValue::NotSet)
+                }
+            };
+
+            if is_score_derived {
+                summary.guesses_derived += 1;
+            } else {
+                summary.guesses_official += 1;
+            }
+
+            if score != guess.score || Some(is_score_derived) != guess.is_score_derived || player_id.is_set() {
+                changed_guesses.push(GuessModel {
+                    id: ActiveValue::Unchanged(guess.id),
+                    score: ActiveValue::Set(score),
+                    is_score_derived: ActiveValue::Set(Some(is_score_derived)),
+                    player_id,
+                    ..Default::default()
+                });
+            }
+        }
+
+        summary.games_updated += changed_games.len();
+        summary.guesses_updated += changed_guesses.len();
+        update_in_transaction(changed_games, db).await?;
+        update_in_transaction(changed_guesses, db).await?;
+    }
+
+    Ok(summary)
+}
+
+/// Starts rescoring in the background, the result is logged once it is done.
+#[post("/admin/rescore")]
+async fn rescore(
+    db: web::Data<DatabaseConnection>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    check_admin_token(&http_request)?;
+
+    let guard = RESCORE_LOCK
+        .clone()
+        .try_lock_owned()
+        .map_err(|_| ErrorConflict("Rescoring is already running!"))?;
+
+    let db = db.get_ref().clone();
+
+    // Fetching from GeoGuessr is not `Send`, so the job runs on the worker's local task set.
+    actix_web::rt::spawn(async move {
+        let _guard = guard;
+
+        match run_rescore(&db).await {
+            Ok(summary) => info!(
+                "Rescoring checked {} games ({} fetched, {} failed, {} ratings updated), {} derived scores, {} official scores, {} guesses attributed, {} guesses updated",
+                summary.games_checked, summary.games_fetched, summary.games_failed, summary.games_updated, summary.guesses_derived,
+                summary.guesses_official, summary.guesses_attributed, summary.guesses_updated
+            ),
+            Err(err) => error!("Could not rescore! Error: {}", err)
+        }
+    });
+
+    Ok(HttpResponse::Accepted().finish())
+}
@@ -17,6 +17,8 @@ pub struct Model {
     pub map_id: String,
     pub rating_before_team1: Option<i32>,
     pub rating_before_team2: Option<i32>,
+    pub rating_after_team1: Option<i32>,
+    pub rating_after_team2: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -24,7 +26,9 @@ pub enum Relation {
     #[sea_orm(has_many = "super::duels_round::Entity")]
     Rounds,
     #[sea_orm(has_many = "super::guess::Entity")]
-    Guesses
+    Guesses,
+    #[sea_orm(has_many = "super::round_result::Entity")]
+    RoundResults
 }
 
 impl Related<super::duels_round::Entity> for Entity {
@@ -39,4 +43,10 @@ impl Related<super::guess::Entity> for Entity {
     }
 }
 
+impl Related<super::round_result::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoundResults.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
@@ -14,6 +14,9 @@ pub struct Model {
     pub round_number: i32,
     #[sea_orm(column_type = "Double")]
     pub damage_multiplier: f64,
+    #[sea_orm(column_type = "Double")]
+    pub multiplier: f64,
+    pub is_healing_round: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -27,7 +30,9 @@ pub enum Relation {
     #[sea_orm(has_many = "super::guess::Entity")]
     Guesses,
     #[sea_orm(has_one = "super::location::Entity")]
-    Location
+    Location,
+    #[sea_orm(has_many = "super::round_result::Entity")]
+    RoundResults
 }
 
 impl Related<super::duels_game::Entity> for Entity {
@@ -48,4 +53,10 @@ impl Related<super::location::Entity> for Entity {
     }
 }
 
+impl Related<super::round_result::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RoundResults.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
\ No newline at end of file
@@ -11,6 +11,7 @@ pub struct Model {
     pub game_id: String,
     pub round_id: String,
     pub team_id: String,
+    pub player_id: Option<String>,
     #[sea_orm(column_type = "Double")]
     pub lat: f64,
     #[sea_orm(column_type = "Double")]
@@ -24,6 +25,9 @@ pub struct Model {
     pub subdivision_code: Option<String>,
     pub round_country_code: String,
     pub is_teams_best: bool,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub border_distance: Option<f64>,
+    pub is_score_derived: Option<bool>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,14 +2,21 @@
 
 pub mod prelude;
 
+pub mod br_game;
+pub mod br_guess;
+pub mod br_player;
+pub mod br_round;
 pub mod comp_team;
 pub mod duels_game;
 pub mod duels_round;
 pub mod fun_team;
+pub mod geocoding_run;
 pub mod guess;
+pub mod leaderboard_entry;
 pub mod location;
 pub mod map;
 pub mod player;
+pub mod round_result;
 pub mod session;
 pub mod solo_game;
 pub mod solo_round;
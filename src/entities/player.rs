@@ -3,7 +3,7 @@
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
 #[sea_orm(table_name = "player")]
 #[serde(rename_all = "camelCase")]
 pub struct Model {
@@ -19,6 +19,8 @@ pub struct Model {
     pub moving_rating: Option<i32>,
     pub no_move_rating: Option<i32>,
     pub nmpz_rating: Option<i32>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub guessed_first_rate: Option<f64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
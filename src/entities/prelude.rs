@@ -1,13 +1,20 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.10
 
+pub use super::br_game::Entity as BrGame;
+pub use super::br_guess::Entity as BrGuess;
+pub use super::br_player::Entity as BrPlayer;
+pub use super::br_round::Entity as BrRound;
 pub use super::comp_team::Entity as CompTeam;
 pub use super::duels_game::Entity as DuelsGame;
 pub use super::duels_round::Entity as DuelsRound;
 pub use super::fun_team::Entity as FunTeam;
+pub use super::geocoding_run::Entity as GeocodingRun;
 pub use super::guess::Entity as Guess;
+pub use super::leaderboard_entry::Entity as LeaderboardEntry;
 pub use super::location::Entity as Location;
 pub use super::map::Entity as Map;
 pub use super::player::Entity as Player;
+pub use super::round_result::Entity as RoundResult;
 pub use super::session::Entity as Session;
 pub use super::solo_game::Entity as SoloGame;
 pub use super::solo_round::Entity as SoloRound;
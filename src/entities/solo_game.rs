@@ -11,6 +11,9 @@ pub struct Model {
     pub geo_mode: String,
     pub start_time: String,
     pub map_id: String,
+    pub streak_type: Option<String>,
+    pub streak_length: Option<i32>,
+    pub streak_end_location_code: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
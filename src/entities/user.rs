@@ -11,6 +11,7 @@ pub struct Model {
     pub salt: String,
     pub salted_password_hash: String,
     pub player_id: Option<String>,
+    pub is_profile_public: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
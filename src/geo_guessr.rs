@@ -39,6 +39,42 @@ impl FromStr for TeamGameMode {
     }
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+pub enum StreakType {
+    CountryStreak,
+    UsStateStreak
+}
+
+impl FromStr for StreakType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "CountryStreak" => Ok(StreakType::CountryStreak),
+            "UsStateStreak" => Ok(StreakType::UsStateStreak),
+            _ => Err(Error)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub enum BattleRoyaleMode {
+    BattleRoyaleCountries,
+    BattleRoyaleDistance
+}
+
+impl FromStr for BattleRoyaleMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "BattleRoyaleCountries" => Ok(BattleRoyaleMode::BattleRoyaleCountries),
+            "BattleRoyaleDistance" => Ok(BattleRoyaleMode::BattleRoyaleDistance),
+            _ => Err(Error)
+        }
+    }
+}
+
 impl fmt::Display for GeoMode {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -51,6 +87,18 @@ impl fmt::Display for TeamGameMode {
     }
 }
 
+impl fmt::Display for StreakType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Display for BattleRoyaleMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RankedTeam {
@@ -370,6 +418,61 @@ pub struct Team {
     pub round_results: Vec<RoundResults>
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleRoyaleGame {
+    pub game_id: String,
+    pub status: String,
+    pub is_distance_game: bool,
+    pub current_round_number: i32,
+    pub map_slug: Option<String>,
+    pub movement_options: MovementOption,
+    pub rounds: Vec<BattleRoyaleRound>,
+    pub players: Vec<BattleRoyalePlayer>
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleRoyaleRound {
+    pub round_number: i32,
+    pub pano_id: String,
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(default)]
+    pub heading: f64,
+    #[serde(default)]
+    pub pitch: f64,
+    #[serde(default)]
+    pub zoom: f64,
+    pub country_code: String,
+    pub start_time: Option<String>
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleRoyalePlayer {
+    pub player_id: String,
+    pub nick: String,
+    /// Lives left at the end of the game.
+    pub lives: i32,
+    pub knocked_out_at_round: Option<i32>,
+    pub guesses: Vec<BattleRoyaleGuess>
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BattleRoyaleGuess {
+    pub round_number: i32,
+    pub lat: f64,
+    pub lng: f64,
+    /// Selected country in countries games.
+    pub country_code: Option<String>,
+    /// Only reported in distance games.
+    pub distance: Option<f64>,
+    pub is_correct: Option<bool>,
+    pub created: String
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RoundResults {
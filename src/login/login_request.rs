@@ -4,6 +4,7 @@ use crate::entities::session::ActiveModel as SessionModel;
 use crate::entities::user::ActiveModel as UserModel;
 use crate::entities::{user};
 use crate::login::email::send_verify_email;
+use crate::login::get_user_from_session;
 use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorGone, ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized};
 use actix_web::{post, web, Error, HttpRequest, HttpResponse, Responder};
 use chrono::{DateTime, Duration, TimeDelta, Utc};
@@ -21,6 +22,7 @@ use sea_orm::prelude::Expr;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 use crate::geo_guessr::{GameModeRatings, PlayerRankedSystemProgress};
+use crate::requests::geo_login;
 
 const SESSION_EXPIRE: TimeDelta = Duration::days(30);
 const SESSION_EXPIRE_DURATION: time::Duration = time::Duration::days(30);
@@ -70,6 +72,12 @@ struct UserLinkAccountRequest {
     player_id: String
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileVisibilityRequest {
+    is_public: bool
+}
+
 async fn create_new_session(user_id: String, db: &DatabaseConnection) -> Result<String, Error> {
     let session_id = Uuid::new_v4().to_string();
     
@@ -119,28 +127,26 @@ async fn insert_player_model(player_id: &str, db: &DatabaseConnection, client: &
             return Ok(());
         }
         
-        let player_response = client.get(format!("https://www.geoguessr.com/api/v3/users/{}", player_id))
-            .send()
-            .await
-            .map_err(|_| ErrorInternalServerError("Fetch User operation failed!"))?
+        let player_response = geo_login::get(client, &format!("https://www.geoguessr.com/api/v3/users/{}", player_id))
+            .await?
             .json::<crate::geo_guessr::User>()
             .await
             .map_err(|_| ErrorNotFound(format!("User with id {} could not be found!", player_id)))?;
 
-        let player_ratings_option = client.get(format!("https://www.geoguessr.com/api/v4/ranked-system/progress/{}", player_id))
-            .send()
-            .await
-            .map_err(|_| ErrorInternalServerError(format!("Fetch Player Ratings operation failed for player {}!", player_id)))?
+        let player_ratings_option = geo_login::get(client, &format!("https://www.geoguessr.com/api/v4/ranked-system/progress/{}", player_id))
+            .await?
             .json::<PlayerRankedSystemProgress>()
             .await
             .ok();
 
         let player_rating;
+        let guessed_first_rate;
         let game_mode_ratings;
         
         match player_ratings_option {
             Some(player_ratings) => {
                 player_rating = player_ratings.rating;
+                guessed_first_rate = Some(player_ratings.guessed_first_rate);
 
                 if let Some(game_mode_ratings_result) = player_ratings.game_mode_ratings {
                     game_mode_ratings = game_mode_ratings_result;
@@ -154,6 +160,7 @@ async fn insert_player_model(player_id: &str, db: &DatabaseConnection, client: &
             },
             None => {
                 player_rating = None;
+                guessed_first_rate = None;
 
                 game_mode_ratings = GameModeRatings {
                     standard_duels: None,
@@ -168,6 +175,7 @@ async fn insert_player_model(player_id: &str, db: &DatabaseConnection, client: &
             name: ActiveValue::Set(player_response.nick),
             country_code: ActiveValue::Set(player_response.country_code),
             rating: ActiveValue::Set(player_rating),
+            guessed_first_rate: ActiveValue::Set(guessed_first_rate),
             moving_rating: ActiveValue::Set(game_mode_ratings.standard_duels),
             no_move_rating: ActiveValue::Set(game_mode_ratings.no_move_duels),
             nmpz_rating: ActiveValue::Set(game_mode_ratings.nmpz_duels),
@@ -313,6 +321,7 @@ async fn verify_email(
         salt: ActiveValue::Set(user.salt),
         salted_password_hash: ActiveValue::Set(user.salted_password_hash),
         player_id: ActiveValue::NotSet,
+        is_profile_public: ActiveValue::Set(false),
     };
     
     match User::insert(user).exec(db).await {
@@ -379,6 +388,35 @@ async fn link_account(
     Ok(HttpResponse::Ok())    
 }
 
+#[post("/profile-visibility")]
+async fn set_profile_visibility(
+    db: web::Data<DatabaseConnection>,
+    request: web::Json<ProfileVisibilityRequest>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let user = get_user_from_session(&session_id, db).await?;
+
+    User::update_many()
+        .col_expr(
+            user::Column::IsProfilePublic,
+            Expr::value(request.is_public)
+        )
+        .filter(user::Column::Id.eq(user.id))
+        .exec(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok())
+}
+
 #[post("/logout")]
 async fn log_out(
     db: web::Data<DatabaseConnection>,
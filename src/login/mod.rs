@@ -1,10 +1,11 @@
 use crate::entities::prelude::{Session, User, Player};
 use crate::entities::user::Model as UserModel;
 use crate::entities::player::Model as PlayerModel;
-use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorGone, ErrorInternalServerError};
+use actix_web::error::{ErrorBadRequest, ErrorConflict, ErrorGone, ErrorInternalServerError, ErrorNotFound};
 use actix_web::Error;
 use chrono::{DateTime, Utc};
-use sea_orm::{DatabaseConnection, EntityTrait, ModelTrait};
+use crate::entities::user;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter};
 
 pub mod login_request;
 mod email;
@@ -62,4 +63,19 @@ pub async fn get_player_from_session(session_id: &str, db: &DatabaseConnection)
         },
         Err(err) => Err(ErrorInternalServerError(err.to_string()))
     }
-}
\ No newline at end of file
+}
+
+/// Returns the player if a user linked to it made their profile public.
+pub async fn get_public_player(player_id: &str, db: &DatabaseConnection) -> Result<PlayerModel, Error> {
+    match User::find()
+        .filter(user::Column::PlayerId.eq(player_id))
+        .filter(user::Column::IsProfilePublic.eq(true))
+        .find_also_related(Player)
+        .one(db)
+        .await
+    {
+        Ok(Some((_, Some(player)))) => Ok(player),
+        Ok(_) => Err(ErrorNotFound("Player not found or profile is not public!")),
+        Err(err) => Err(ErrorInternalServerError(err.to_string()))
+    }
+}
@@ -1,20 +1,38 @@
+mod admin;
 mod entities;
 mod geo_guessr;
 mod migrator;
 mod requests;
 mod login;
 
-use crate::login::login_request::{link_account, log_out, user_login, user_signup, verify_email};
-use crate::requests::general_stats_requests::get_general_stats;
+use crate::admin::regeocode::{get_regeocode_status, regeocode};
+use crate::admin::rescore::rescore;
+use crate::login::login_request::{link_account, log_out, set_profile_visibility, user_login, user_signup, verify_email};
+use crate::requests::general_stats_requests::{get_general_stats, get_public_general_stats};
 use crate::requests::import_games::import_recent_games;
-use crate::requests::insertion_requests::{insert_duels_game, insert_solo_game};
+use crate::requests::import_file::import_game_file;
+use crate::requests::insertion_requests::{insert_battle_royale_game, insert_duels_game, insert_solo_game};
 use actix_cors::Cors;
 use actix_web::web::Data;
 use actix_web::{App, HttpServer};
 use dotenv::dotenv;
 use sea_orm::{Database, DbErr};
 use std::env;
-use crate::requests::country_stats_request::get_country_stats;
+use crate::requests::country_stats_request::{get_country_stats, get_public_country_stats};
+use crate::requests::game_requests::{get_game, get_games};
+use crate::requests::opponent_stats_requests::{get_opponent_stats, get_opponents_stats};
+use crate::requests::team_stats_requests::get_teams_stats;
+use crate::requests::leaderboard_requests::{get_leaderboard, spawn_leaderboard_refresh};
+use crate::requests::location_requests::{get_hardest_locations, get_location_stats};
+use crate::requests::heatmap_requests::get_heatmap;
+use crate::requests::export_requests::export_games;
+use crate::requests::timing_requests::get_timing_stats;
+use crate::requests::guess_order_requests::get_guess_order_stats;
+use crate::requests::distance_stats_requests::get_distance_stats;
+use crate::requests::recommendation_requests::get_recommendations;
+use crate::requests::map_requests::{get_map_distribution, get_maps, get_maps_stats};
+use crate::requests::streak_requests::get_streak_stats;
+use crate::requests::geocoder::init_geocoder;
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
@@ -32,20 +50,53 @@ async fn main() -> std::io::Result<()> {
             std::process::exit(1);
         });
 
+    init_geocoder().unwrap_or_else(|err| {
+        eprintln!("Failed loading the geocoder: {}", err);
+        std::process::exit(1);
+    });
+
+    spawn_leaderboard_refresh(db.clone());
+
     HttpServer::new(move || {
         App::new()
             .wrap(Cors::permissive())
             .app_data(Data::new(db.clone()))
             .service(insert_duels_game)
             .service(insert_solo_game)
+            .service(insert_battle_royale_game)
             .service(get_general_stats)
+            .service(get_public_general_stats)
             .service(user_login)
             .service(user_signup)
             .service(verify_email)
             .service(link_account)
             .service(log_out)
+            .service(set_profile_visibility)
             .service(import_recent_games)
+            .service(import_game_file)
             .service(get_country_stats)
+            .service(get_public_country_stats)
+            .service(get_games)
+            .service(get_game)
+            .service(get_opponents_stats)
+            .service(get_opponent_stats)
+            .service(get_teams_stats)
+            .service(get_leaderboard)
+            .service(get_hardest_locations)
+            .service(get_location_stats)
+            .service(get_heatmap)
+            .service(export_games)
+            .service(get_timing_stats)
+            .service(get_guess_order_stats)
+            .service(get_distance_stats)
+            .service(get_recommendations)
+            .service(get_maps)
+            .service(get_map_distribution)
+            .service(get_maps_stats)
+            .service(get_streak_stats)
+            .service(regeocode)
+            .service(get_regeocode_status)
+            .service(rescore)
     })
     .bind(("127.0.0.1", 8080))?
     .run()
@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000013_create_geocoding_run_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(GeocodingRun::Table)
+                    .col(
+                        ColumnDef::new(GeocodingRun::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(GeocodingRun::DatasetVersion).string().not_null())
+                    .col(ColumnDef::new(GeocodingRun::StartTime).string().not_null())
+                    .col(ColumnDef::new(GeocodingRun::EndTime).string().not_null())
+                    .col(ColumnDef::new(GeocodingRun::GuessesChecked).integer().not_null())
+                    .col(ColumnDef::new(GeocodingRun::GuessesChanged).integer().not_null())
+                    .col(ColumnDef::new(GeocodingRun::LocationsChecked).integer().not_null())
+                    .col(ColumnDef::new(GeocodingRun::LocationsChanged).integer().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GeocodingRun::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum GeocodingRun {
+    Table,
+    Id,
+    DatasetVersion,
+    StartTime,
+    EndTime,
+    GuessesChecked,
+    GuessesChanged,
+    LocationsChecked,
+    LocationsChanged
+}
@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000014_add_guess_border_distance"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Guess::Table)
+                    .add_column(ColumnDef::new(Guess::BorderDistance).double())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Guess::Table)
+                    .drop_column(Guess::BorderDistance)
+                    .to_owned()
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Guess {
+    Table,
+    BorderDistance
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000015_add_guess_is_score_derived"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Guess::Table)
+                    .add_column(ColumnDef::new(Guess::IsScoreDerived).boolean())
+                    .to_owned()
+            )
+            .await?;
+
+        // Solo scores are always reported by GeoGuessr. Duels guesses stay NULL until
+        // `POST /admin/rescore` re-fetches their game to find out where the score came from.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Guess::Table)
+                    .value(Guess::IsScoreDerived, false)
+                    .and_where(
+                        Expr::col(Guess::GameId).in_subquery(
+                            Query::select()
+                                .column(SoloGame::Id)
+                                .from(SoloGame::Table)
+                                .to_owned()
+                        )
+                    )
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Guess::Table)
+                    .drop_column(Guess::IsScoreDerived)
+                    .to_owned()
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Guess {
+    Table,
+    GameId,
+    IsScoreDerived
+}
+
+#[derive(Iden)]
+pub enum SoloGame {
+    Table,
+    Id
+}
@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000016_create_round_result_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RoundResult::Table)
+                    .col(
+                        ColumnDef::new(RoundResult::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(RoundResult::GameId).string().not_null())
+                    .col(ColumnDef::new(RoundResult::RoundId).string().not_null())
+                    .col(ColumnDef::new(RoundResult::TeamId).string().not_null())
+                    .col(ColumnDef::new(RoundResult::RoundNumber).integer().not_null())
+                    .col(ColumnDef::new(RoundResult::Score).integer().not_null())
+                    .col(ColumnDef::new(RoundResult::HealthBefore).integer().not_null())
+                    .col(ColumnDef::new(RoundResult::HealthAfter).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DuelsRound::Table)
+                    .add_column(
+                        ColumnDef::new(DuelsRound::Multiplier)
+                            .double()
+                            .not_null()
+                            .default(1.0)
+                    )
+                    .add_column(
+                        ColumnDef::new(DuelsRound::IsHealingRound)
+                            .boolean()
+                            .not_null()
+                            .default(false)
+                    )
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DuelsRound::Table)
+                    .drop_column(DuelsRound::Multiplier)
+                    .drop_column(DuelsRound::IsHealingRound)
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(RoundResult::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum RoundResult {
+    Table,
+    Id,
+    GameId,
+    RoundId,
+    TeamId,
+    RoundNumber,
+    Score,
+    HealthBefore,
+    HealthAfter
+}
+
+#[derive(Iden)]
+pub enum DuelsRound {
+    Table,
+    Multiplier,
+    IsHealingRound
+}
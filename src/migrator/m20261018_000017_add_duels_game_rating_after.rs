@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000017_add_duels_game_rating_after"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DuelsGame::Table)
+                    .add_column(ColumnDef::new(DuelsGame::RatingAfterTeam1).integer())
+                    .add_column(ColumnDef::new(DuelsGame::RatingAfterTeam2).integer())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(DuelsGame::Table)
+                    .drop_column(DuelsGame::RatingAfterTeam1)
+                    .drop_column(DuelsGame::RatingAfterTeam2)
+                    .to_owned()
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum DuelsGame {
+    Table,
+    RatingAfterTeam1,
+    RatingAfterTeam2
+}
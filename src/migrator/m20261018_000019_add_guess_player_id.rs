@@ -0,0 +1,86 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000019_add_guess_player_id"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Guess::Table)
+                    .add_column(ColumnDef::new(Guess::PlayerId).string())
+                    .to_owned()
+            )
+            .await?;
+
+        // In 1v1 duels and solo games the team id is the player id. Team games are
+        // backfilled by re-fetching them with `POST /admin/rescore`.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Guess::Table)
+                    .value(Guess::PlayerId, Expr::col(Guess::TeamId))
+                    .cond_where(
+                        Cond::any()
+                            .add(
+                                Expr::col(Guess::GameId).in_subquery(
+                                    Query::select()
+                                        .column(DuelsGame::Id)
+                                        .from(DuelsGame::Table)
+                                        .and_where(Expr::col(DuelsGame::TeamGameMode).is_in(["Duels", "DuelsRanked"]))
+                                        .to_owned()
+                                )
+                            )
+                            .add(
+                                Expr::col(Guess::GameId).in_subquery(
+                                    Query::select()
+                                        .column(SoloGame::Id)
+                                        .from(SoloGame::Table)
+                                        .to_owned()
+                                )
+                            )
+                    )
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Guess::Table)
+                    .drop_column(Guess::PlayerId)
+                    .to_owned()
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Guess {
+    Table,
+    GameId,
+    TeamId,
+    PlayerId
+}
+
+#[derive(Iden)]
+pub enum DuelsGame {
+    Table,
+    Id,
+    TeamGameMode
+}
+
+#[derive(Iden)]
+pub enum SoloGame {
+    Table,
+    Id
+}
@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000020_create_leaderboard_entry_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(LeaderboardEntry::Table)
+                    .col(
+                        ColumnDef::new(LeaderboardEntry::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(LeaderboardEntry::Kind).string().not_null())
+                    .col(ColumnDef::new(LeaderboardEntry::GeoMode).string())
+                    .col(ColumnDef::new(LeaderboardEntry::MapId).string())
+                    .col(ColumnDef::new(LeaderboardEntry::Region).string())
+                    .col(ColumnDef::new(LeaderboardEntry::Rank).integer().not_null())
+                    .col(ColumnDef::new(LeaderboardEntry::PlayerId).string().not_null())
+                    .col(ColumnDef::new(LeaderboardEntry::Value).double().not_null())
+                    .col(ColumnDef::new(LeaderboardEntry::Rounds).integer().not_null())
+                    .col(ColumnDef::new(LeaderboardEntry::ComputedAt).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(LeaderboardEntry::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum LeaderboardEntry {
+    Table,
+    Id,
+    Kind,
+    GeoMode,
+    MapId,
+    Region,
+    Rank,
+    PlayerId,
+    Value,
+    Rounds,
+    ComputedAt
+}
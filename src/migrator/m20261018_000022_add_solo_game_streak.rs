@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000022_add_solo_game_streak"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SoloGame::Table)
+                    .add_column(ColumnDef::new(SoloGame::StreakType).string().null())
+                    .add_column(ColumnDef::new(SoloGame::StreakLength).integer().null())
+                    .add_column(ColumnDef::new(SoloGame::StreakEndLocationCode).string().null())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SoloGame::Table)
+                    .drop_column(SoloGame::StreakType)
+                    .drop_column(SoloGame::StreakLength)
+                    .drop_column(SoloGame::StreakEndLocationCode)
+                    .to_owned()
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum SoloGame {
+    Table,
+    StreakType,
+    StreakLength,
+    StreakEndLocationCode
+}
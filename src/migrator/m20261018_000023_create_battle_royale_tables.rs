@@ -0,0 +1,170 @@
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20261018_000023_create_battle_royale_tables"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BrGame::Table)
+                    .col(
+                        ColumnDef::new(BrGame::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(BrGame::GameMode).string().not_null())
+                    .col(ColumnDef::new(BrGame::GeoMode).string().not_null())
+                    .col(ColumnDef::new(BrGame::StartTime).string().not_null())
+                    .col(ColumnDef::new(BrGame::MapId).string().null())
+                    .col(ColumnDef::new(BrGame::RoundCount).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BrPlayer::Table)
+                    .col(
+                        ColumnDef::new(BrPlayer::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(BrPlayer::GameId).string().not_null())
+                    .col(ColumnDef::new(BrPlayer::PlayerId).string().not_null())
+                    .col(ColumnDef::new(BrPlayer::Nick).string().not_null())
+                    .col(ColumnDef::new(BrPlayer::Lives).integer().not_null())
+                    .col(ColumnDef::new(BrPlayer::KnockedOutAtRound).integer().null())
+                    .col(ColumnDef::new(BrPlayer::Position).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BrRound::Table)
+                    .col(
+                        ColumnDef::new(BrRound::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(BrRound::GameId).string().not_null())
+                    .col(ColumnDef::new(BrRound::LocationId).string().not_null())
+                    .col(ColumnDef::new(BrRound::RoundCountryCode).string().not_null())
+                    .col(ColumnDef::new(BrRound::RoundNumber).integer().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(BrGuess::Table)
+                    .col(
+                        ColumnDef::new(BrGuess::Id)
+                            .string()
+                            .not_null()
+                            .primary_key()
+                    )
+                    .col(ColumnDef::new(BrGuess::GameId).string().not_null())
+                    .col(ColumnDef::new(BrGuess::RoundId).string().not_null())
+                    .col(ColumnDef::new(BrGuess::PlayerId).string().not_null())
+                    .col(ColumnDef::new(BrGuess::Lat).double().not_null())
+                    .col(ColumnDef::new(BrGuess::Lng).double().not_null())
+                    .col(ColumnDef::new(BrGuess::CountryCode).string().null())
+                    .col(ColumnDef::new(BrGuess::SubdivisionCode).string().null())
+                    .col(ColumnDef::new(BrGuess::RoundCountryCode).string().not_null())
+                    .col(ColumnDef::new(BrGuess::Distance).double().not_null())
+                    .col(ColumnDef::new(BrGuess::IsCorrect).boolean().not_null())
+                    .col(ColumnDef::new(BrGuess::LivesAfter).integer().null())
+                    .col(ColumnDef::new(BrGuess::Position).integer().null())
+                    .col(ColumnDef::new(BrGuess::Time).integer().null())
+                    .col(ColumnDef::new(BrGuess::Date).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BrGuess::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BrRound::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BrPlayer::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(BrGame::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum BrGame {
+    Table,
+    Id,
+    GameMode,
+    GeoMode,
+    StartTime,
+    MapId,
+    RoundCount
+}
+
+#[derive(Iden)]
+pub enum BrPlayer {
+    Table,
+    Id,
+    GameId,
+    PlayerId,
+    Nick,
+    Lives,
+    KnockedOutAtRound,
+    Position
+}
+
+#[derive(Iden)]
+pub enum BrRound {
+    Table,
+    Id,
+    GameId,
+    LocationId,
+    RoundCountryCode,
+    RoundNumber
+}
+
+#[derive(Iden)]
+pub enum BrGuess {
+    Table,
+    Id,
+    GameId,
+    RoundId,
+    PlayerId,
+    Lat,
+    Lng,
+    CountryCode,
+    SubdivisionCode,
+    RoundCountryCode,
+    Distance,
+    IsCorrect,
+    LivesAfter,
+    Position,
+    Time,
+    Date
+}
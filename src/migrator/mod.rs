@@ -10,6 +10,18 @@ mod m20250420_00009_create_fun_team_table;
 mod m20250420_000010_create_map_table;
 mod m20250504_000011_create_user_table;
 mod m20250420_000012_create_session_table;
+mod m20261018_000013_create_geocoding_run_table;
+mod m20261018_000014_add_guess_border_distance;
+mod m20261018_000015_add_guess_is_score_derived;
+mod m20261018_000016_create_round_result_table;
+mod m20261018_000017_add_duels_game_rating_after;
+mod m20261018_000018_add_user_is_profile_public;
+mod m20261018_000019_add_guess_player_id;
+mod m20261018_000020_create_leaderboard_entry_table;
+mod m20261018_000021_add_player_guessed_first_rate;
+mod m20261018_000022_add_solo_game_streak;
+mod m20261018_000023_create_battle_royale_tables;
+mod m20261018_000024_add_round_result_best_guess;
 
 use sea_orm_migration::prelude::*;
 
@@ -30,7 +42,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20250420_00009_create_fun_team_table::Migration),
             Box::new(m20250420_000010_create_map_table::Migration),
             Box::new(m20250504_000011_create_user_table::Migration),
-            Box::new(m20250420_000012_create_session_table::Migration)
+            Box::new(m20250420_000012_create_session_table::Migration),
+            Box::new(m20261018_000013_create_geocoding_run_table::Migration),
+            Box::new(m20261018_000014_add_guess_border_distance::Migration),
+            Box::new(m20261018_000015_add_guess_is_score_derived::Migration),
+            Box::new(m20261018_000016_create_round_result_table::Migration),
+            Box::new(m20261018_000017_add_duels_game_rating_after::Migration),
+            Box::new(m20261018_000018_add_user_is_profile_public::Migration),
+            Box::new(m20261018_000019_add_guess_player_id::Migration),
+            Box::new(m20261018_000020_create_leaderboard_entry_table::Migration),
+            Box::new(m20261018_000021_add_player_guessed_first_rate::Migration),
+            Box::new(m20261018_000022_add_solo_game_streak::Migration),
+            Box::new(m20261018_000023_create_battle_royale_tables::Migration),
+            Box::new(m20261018_000024_add_round_result_best_guess::Migration)
         ]
     }
 }
\ No newline at end of file
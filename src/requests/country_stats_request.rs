@@ -1,8 +1,8 @@
 use crate::entities::player::Model as PlayerModel;
-use crate::entities::prelude::{CompTeam, DuelsGame};
-use crate::entities::{comp_team, duels_game, duels_round, guess, location};
-use crate::geo_guessr::TeamGameMode;
-use crate::login::get_player_from_session;
+use crate::entities::prelude::{BrGame, BrGuess, BrPlayer, BrRound, CompTeam, DuelsGame, Location};
+use crate::entities::{br_game, br_guess, br_player, br_round, comp_team, duels_game, duels_round, guess, location};
+use crate::geo_guessr::{BattleRoyaleMode, TeamGameMode};
+use crate::login::{get_player_from_session, get_public_player};
 use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
 use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
 use chrono::{DateTime, Utc};
@@ -10,6 +10,7 @@ use sea_orm::QueryFilter;
 use sea_orm::{ColumnTrait, LoaderTrait};
 use sea_orm::{DatabaseConnection, EntityTrait};
 use serde::Serialize;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
@@ -42,6 +43,27 @@ struct TeamStatsGuess {
     enemy_team_guesses: Vec<SingleGuess>
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BattleRoyaleSingleGuess {
+    time: Option<i32>,
+    lat: f64,
+    lon: f64,
+    distance: f64,
+    is_correct: bool,
+    country_code: Option<String>,
+    subdivision_code: Option<String>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BattleRoyaleStatsGuess {
+    game_start_time: i64,
+    round_subdivision_code: Option<String>,
+    /// Countries games allow several guesses per round until one is correct.
+    player_guesses: Vec<BattleRoyaleSingleGuess>
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct Stats {
@@ -50,7 +72,9 @@ struct Stats {
     duels_ranked: Vec<StatsGuess>,
     team_duels: Vec<TeamStatsGuess>,
     team_duels_ranked: Vec<TeamStatsGuess>,
-    team_fun: Vec<TeamStatsGuess>
+    team_fun: Vec<TeamStatsGuess>,
+    battle_royale_countries: Vec<BattleRoyaleStatsGuess>,
+    battle_royale_distance: Vec<BattleRoyaleStatsGuess>
 }
 
 #[derive(Serialize)]
@@ -118,22 +142,99 @@ fn get_duels_guess(guesses: Vec<guess::Model>, team_ids: &HashSet<String>, date:
     }
 }
 
-#[get("/country/{country_code}")]
-pub async fn get_country_stats(
-    db: web::Data<DatabaseConnection>,
-    path: web::Path<String>,
-    http_request: HttpRequest
-) -> Result<impl Responder, Error> {
-    let session_id = match http_request.cookie("sessionId") {
-        Some(cookie) => {
-            String::from(cookie.value())
-        },
-        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
-    };
+/// Rounds of the player's battle royale games in the country, split into countries and distance games.
+async fn get_battle_royale_guesses(
+    player_id: &str,
+    country_code: &str,
+    db: &DatabaseConnection
+) -> Result<(Vec<BattleRoyaleStatsGuess>, Vec<BattleRoyaleStatsGuess>), Error> {
+    let game_ids: Vec<String> = BrPlayer::find()
+        .filter(br_player::Column::PlayerId.eq(player_id))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|player| player.game_id)
+        .collect();
 
-    let db = db.get_ref();
-    let country_code = path.into_inner().to_ascii_uppercase();
-    let player = get_player_from_session(&session_id, db).await?;
+    let games: HashMap<String, br_game::Model> = BrGame::find()
+        .filter(br_game::Column::Id.is_in(&game_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| (game.id.clone(), game))
+        .collect();
+
+    let rounds = BrRound::find()
+        .filter(br_round::Column::GameId.is_in(&game_ids))
+        .filter(br_round::Column::RoundCountryCode.eq(country_code))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut round_guesses: HashMap<String, Vec<br_guess::Model>> = HashMap::new();
+
+    for guess in BrGuess::find()
+        .filter(br_guess::Column::RoundId.is_in(rounds.iter().map(|round| round.id.clone())))
+        .filter(br_guess::Column::PlayerId.eq(player_id))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+    {
+        round_guesses.entry(guess.round_id.clone()).or_default().push(guess);
+    }
+
+    let locations: HashMap<String, location::Model> = Location::find()
+        .filter(location::Column::Id.is_in(rounds.iter().map(|round| round.location_id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|location| (location.id.clone(), location))
+        .collect();
+
+    let mut battle_royale_countries = Vec::new();
+    let mut battle_royale_distance = Vec::new();
+
+    for round in rounds {
+        let (Some(game), Some(mut guesses)) = (games.get(&round.game_id), round_guesses.remove(&round.id)) else {
+            continue;
+        };
+
+        guesses.sort_unstable_by(|a, b| a.date.cmp(&b.date));
+
+        let date: DateTime<Utc> = game.start_time.parse().map_err(ErrorInternalServerError)?;
+        let stats_guess = BattleRoyaleStatsGuess {
+            game_start_time: date.timestamp_millis(),
+            round_subdivision_code: locations.get(&round.location_id).and_then(|location| location.subdivision_code.clone()),
+            player_guesses: guesses
+                .into_iter()
+                .map(|guess| BattleRoyaleSingleGuess {
+                    time: guess.time,
+                    lat: guess.lat,
+                    lon: guess.lng,
+                    distance: guess.distance,
+                    is_correct: guess.is_correct,
+                    country_code: guess.country_code,
+                    subdivision_code: guess.subdivision_code
+                })
+                .collect()
+        };
+
+        match BattleRoyaleMode::from_str(&game.game_mode).map_err(ErrorInternalServerError)? {
+            BattleRoyaleMode::BattleRoyaleCountries => battle_royale_countries.push(stats_guess),
+            BattleRoyaleMode::BattleRoyaleDistance => battle_royale_distance.push(stats_guess)
+        }
+    }
+
+    battle_royale_countries.sort_unstable_by_key(|stats_guess| Reverse(stats_guess.game_start_time));
+    battle_royale_distance.sort_unstable_by_key(|stats_guess| Reverse(stats_guess.game_start_time));
+
+    Ok((battle_royale_countries, battle_royale_distance))
+}
+
+async fn get_country_stats_response(player: PlayerModel, country_code: String, db: &DatabaseConnection) -> Result<HttpResponse, Error> {
     let mut team_ids: HashSet<String> = [player.id.clone()].into_iter().collect();
 
     match CompTeam::find()
@@ -191,13 +292,17 @@ pub async fn get_country_stats(
     team_duels.sort_unstable_by(|a, b| b.game_start_time.cmp(&a.game_start_time));
     team_duels_ranked.sort_unstable_by(|a, b| b.game_start_time.cmp(&a.game_start_time));
     team_fun.sort_unstable_by(|a, b| b.game_start_time.cmp(&a.game_start_time));
+
+    let (battle_royale_countries, battle_royale_distance) = get_battle_royale_guesses(&player.id, &country_code, db).await?;
     
     let stats = Stats {
         duels,
         duels_ranked,
         team_duels,
         team_duels_ranked,
-        team_fun
+        team_fun,
+        battle_royale_countries,
+        battle_royale_distance
     };
     
     let response = CountryStatsResponse {
@@ -206,4 +311,36 @@ pub async fn get_country_stats(
     };
     
     Ok(HttpResponse::Ok().json(response))
-}
\ No newline at end of file
+}
+
+#[get("/country/{country_code}")]
+pub async fn get_country_stats(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let country_code = path.into_inner().to_ascii_uppercase();
+    let player = get_player_from_session(&session_id, db).await?;
+
+    get_country_stats_response(player, country_code, db).await
+}
+
+#[get("/players/{player_id}/country/{country_code}")]
+pub async fn get_public_country_stats(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<(String, String)>
+) -> Result<impl Responder, Error> {
+    let db = db.get_ref();
+    let (player_id, country_code) = path.into_inner();
+    let player = get_public_player(&player_id, db).await?;
+
+    get_country_stats_response(player, country_code.to_ascii_uppercase(), db).await
+}
@@ -0,0 +1,162 @@
+use crate::entities::prelude::{DuelsRound, Guess, Location};
+use crate::entities::{duels_round, guess, location};
+use crate::login::get_player_id_from_session;
+use crate::requests::{get_player_team_ids, GameFilter};
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+const PERCENTILES: [u32; 5] = [10, 25, 50, 75, 90];
+const DIRECTIONS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DistancePercentile {
+    percentile: u32,
+    distance: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountryDistanceStats {
+    country_code: String,
+    guesses: usize,
+    avg_distance: f64,
+    median_distance: f64,
+    percentiles: Vec<DistancePercentile>,
+    /// Mean bearing in degrees from the round location to the guess, clockwise from north.
+    avg_bearing: Option<f64>,
+    /// Compass direction of `avg_bearing`, e.g. `S` if the guesses are usually too far south.
+    direction: Option<&'static str>,
+    /// Length of the mean bearing vector, 1 if every miss points the same way and 0 if they cancel out.
+    bearing_consistency: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DistanceStatsResponse {
+    countries: Vec<CountryDistanceStats>
+}
+
+/// Initial great circle bearing from one coordinate to another in degrees.
+fn get_bearing(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let delta_lng = (lng2 - lng1).to_radians();
+
+    let y = delta_lng.sin() * lat2.cos();
+    let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lng.cos();
+
+    (y.atan2(x).to_degrees() + 360.0) % 360.0
+}
+
+/// Linearly interpolated percentile of sorted values.
+fn get_percentile(sorted_values: &[f64], percentile: u32) -> f64 {
+    let rank = percentile as f64 / 100.0 * (sorted_values.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * (rank - lower as f64)
+}
+
+fn get_country_distance_stats(country_code: String, misses: Vec<(f64, Option<f64>)>) -> CountryDistanceStats {
+    let mut distances: Vec<f64> = misses.iter().map(|(distance, _)| *distance).collect();
+    distances.sort_by(f64::total_cmp);
+
+    let bearings: Vec<f64> = misses.iter().filter_map(|(_, bearing)| *bearing).collect();
+    let (sin_sum, cos_sum) = bearings
+        .iter()
+        .fold((0.0, 0.0), |(sin_sum, cos_sum), bearing| (sin_sum + bearing.to_radians().sin(), cos_sum + bearing.to_radians().cos()));
+
+    let bearing_consistency = if bearings.is_empty() {
+        0.0
+    } else {
+        (sin_sum * sin_sum + cos_sum * cos_sum).sqrt() / bearings.len() as f64
+    };
+
+    let avg_bearing = (bearing_consistency > 0.0).then(|| (sin_sum.atan2(cos_sum).to_degrees() + 360.0) % 360.0);
+
+    CountryDistanceStats {
+        country_code,
+        guesses: distances.len(),
+        avg_distance: distances.iter().sum::<f64>() / distances.len() as f64,
+        median_distance: get_percentile(&distances, 50),
+        percentiles: PERCENTILES
+            .iter()
+            .map(|&percentile| DistancePercentile {
+                percentile,
+                distance: get_percentile(&distances, percentile)
+            })
+            .collect(),
+        avg_bearing,
+        direction: avg_bearing.map(|bearing| DIRECTIONS[((bearing + 22.5) / 45.0) as usize % DIRECTIONS.len()]),
+        bearing_consistency
+    }
+}
+
+#[get("/stats/distances")]
+pub async fn get_distance_stats(
+    db: web::Data<DatabaseConnection>,
+    filter: web::Query<GameFilter>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+    let team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+    let games = filter.find_games(&team_ids, db).await?;
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(games.into_iter().map(|game| game.id)))
+        .filter(guess::Column::TeamId.is_in(&team_ids))
+        .filter(guess::Column::IsTeamsBest.eq(true))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let round_locations: HashMap<String, String> = DuelsRound::find()
+        .filter(duels_round::Column::Id.is_in(guesses.iter().map(|guess| guess.round_id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|round| (round.id, round.location_id))
+        .collect();
+
+    let locations: HashMap<String, location::Model> = Location::find()
+        .filter(location::Column::Id.is_in(round_locations.values().cloned()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|location| (location.id.clone(), location))
+        .collect();
+
+    let mut countries: BTreeMap<String, Vec<(f64, Option<f64>)>> = BTreeMap::new();
+
+    for guess in guesses {
+        let bearing = round_locations
+            .get(&guess.round_id)
+            .and_then(|location_id| locations.get(location_id))
+            .filter(|_| guess.distance > 0.0)
+            .map(|location| get_bearing(location.lat, location.lng, guess.lat, guess.lng));
+
+        countries.entry(guess.round_country_code).or_default().push((guess.distance, bearing));
+    }
+
+    let mut country_stats: Vec<CountryDistanceStats> = countries
+        .into_iter()
+        .map(|(country_code, misses)| get_country_distance_stats(country_code, misses))
+        .collect();
+
+    country_stats.sort_by_key(|stats| Reverse(stats.guesses));
+
+    Ok(HttpResponse::Ok().json(DistanceStatsResponse { countries: country_stats }))
+}
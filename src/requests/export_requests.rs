@@ -0,0 +1,460 @@
+use crate::entities::prelude::{DuelsGame, DuelsRound, Guess, Location, Map, SoloGame, SoloRound};
+use crate::entities::{duels_game, duels_round, guess, location, map, solo_game, solo_round};
+use crate::login::get_player_id_from_session;
+use crate::requests::get_player_team_ids;
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse};
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, Int32Array, StringArray};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use async_stream::try_stream;
+use bytes::Bytes;
+use parquet::arrow::ArrowWriter;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+const GAME_BATCH_SIZE: usize = 50;
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+    Ndjson
+}
+
+impl ExportFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Parquet => "application/vnd.apache.parquet",
+            ExportFormat::Ndjson => "application/x-ndjson"
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Ndjson => "ndjson"
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat
+}
+
+/// A duels or solo game of the session player, with the team the player played in.
+struct ExportGame {
+    id: String,
+    game_type: &'static str,
+    team_game_mode: Option<String>,
+    geo_mode: String,
+    map_id: String,
+    start_time: String,
+    team_id: String
+}
+
+/// One row per guess, joined with its round, location, game and map.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRow {
+    game_id: String,
+    game_type: &'static str,
+    team_game_mode: Option<String>,
+    geo_mode: String,
+    map_id: String,
+    map_name: Option<String>,
+    start_time: String,
+    round_number: i32,
+    pano_id: String,
+    lat: f64,
+    lng: f64,
+    country_code: String,
+    subdivision_code: Option<String>,
+    team_id: String,
+    player_id: Option<String>,
+    is_own_team: bool,
+    guess_lat: f64,
+    guess_lng: f64,
+    guess_country_code: Option<String>,
+    distance: f64,
+    score: i32,
+    time: Option<i32>,
+    date: String,
+    is_teams_best: bool
+}
+
+async fn get_export_games(player_id: &str, db: &DatabaseConnection) -> Result<Vec<ExportGame>, Error> {
+    let team_ids = get_player_team_ids(player_id, db).await.map_err(ErrorInternalServerError)?;
+
+    let duels_games = DuelsGame::find()
+        .filter(duels_game::Column::TeamId1.is_in(&team_ids).or(duels_game::Column::TeamId2.is_in(&team_ids)))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    // Streak rounds are not scored.
+    let solo_games = SoloGame::find()
+        .filter(solo_game::Column::PlayerId.eq(player_id))
+        .filter(solo_game::Column::StreakType.is_null())
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut games: Vec<ExportGame> = duels_games
+        .into_iter()
+        .map(|game| ExportGame {
+            team_id: if team_ids.contains(&game.team_id1) { game.team_id1 } else { game.team_id2 },
+            id: game.id,
+            game_type: "duels",
+            team_game_mode: Some(game.team_game_mode),
+            geo_mode: game.geo_mode,
+            map_id: game.map_id,
+            start_time: game.start_time
+        })
+        .chain(solo_games.into_iter().map(|game| ExportGame {
+            id: game.id,
+            game_type: "solo",
+            team_game_mode: None,
+            geo_mode: game.geo_mode,
+            map_id: game.map_id,
+            start_time: game.start_time,
+            team_id: game.player_id
+        }))
+        .collect();
+
+    games.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+
+    Ok(games)
+}
+
+/// Joins the guesses of a batch of games with their rounds, locations and maps.
+async fn get_export_rows(
+    games: &[ExportGame],
+    maps: &HashMap<String, map::Model>,
+    db: &DatabaseConnection
+) -> Result<Vec<ExportRow>, Error> {
+    let game_ids: Vec<&str> = games.iter().map(|game| game.id.as_str()).collect();
+
+    let mut rounds: HashMap<String, (String, i32)> = DuelsRound::find()
+        .filter(duels_round::Column::GameId.is_in(game_ids.iter().copied()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|round| (round.id, (round.location_id, round.round_number)))
+        .collect();
+
+    rounds.extend(
+        SoloRound::find()
+            .filter(solo_round::Column::GameId.is_in(game_ids.iter().copied()))
+            .all(db)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .into_iter()
+            .map(|round| (round.id, (round.location_id, round.round_number)))
+    );
+
+    let location_ids: HashSet<&String> = rounds.values().map(|(location_id, _)| location_id).collect();
+    let locations: HashMap<String, location::Model> = Location::find()
+        .filter(location::Column::Id.is_in(location_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|location| (location.id.clone(), location))
+        .collect();
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(game_ids.iter().copied()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let games: HashMap<&str, (usize, &ExportGame)> = games
+        .iter()
+        .enumerate()
+        .map(|(position, game)| (game.id.as_str(), (position, game)))
+        .collect();
+
+    let mut rows: Vec<(usize, ExportRow)> = guesses
+        .into_iter()
+        .filter_map(|guess| {
+            let (position, game) = games.get(guess.game_id.as_str())?;
+            let (location_id, round_number) = rounds.get(&guess.round_id)?;
+            let location = locations.get(location_id)?;
+
+            Some((*position, ExportRow {
+                game_id: game.id.clone(),
+                game_type: game.game_type,
+                team_game_mode: game.team_game_mode.clone(),
+                geo_mode: game.geo_mode.clone(),
+                map_id: game.map_id.clone(),
+                map_name: maps.get(&game.map_id).map(|map| map.name.clone()),
+                start_time: game.start_time.clone(),
+                round_number: *round_number,
+                pano_id: location.id.clone(),
+                lat: location.lat,
+                lng: location.lng,
+                country_code: location.country_code.clone(),
+                subdivision_code: location.subdivision_code.clone(),
+                is_own_team: guess.team_id == game.team_id,
+                team_id: guess.team_id,
+                player_id: guess.player_id,
+                guess_lat: guess.lat,
+                guess_lng: guess.lng,
+                guess_country_code: guess.country_code,
+                distance: guess.distance,
+                score: guess.score,
+                time: guess.time,
+                date: guess.date,
+                is_teams_best: guess.is_teams_best
+            }))
+        })
+        .collect();
+
+    rows.sort_by_key(|(position, row)| (*position, row.round_number));
+
+    Ok(rows.into_iter().map(|(_, row)| row).collect())
+}
+
+fn get_export_schema() -> SchemaRef {
+    let string = |name: &str, nullable: bool| Field::new(name, DataType::Utf8, nullable);
+
+    Arc::new(Schema::new(vec![
+        string("gameId", false),
+        string("gameType", false),
+        string("teamGameMode", true),
+        string("geoMode", false),
+        string("mapId", false),
+        string("mapName", true),
+        string("startTime", false),
+        Field::new("roundNumber", DataType::Int32, false),
+        string("panoId", false),
+        Field::new("lat", DataType::Float64, false),
+        Field::new("lng", DataType::Float64, false),
+        string("countryCode", false),
+        string("subdivisionCode", true),
+        string("teamId", false),
+        string("playerId", true),
+        Field::new("isOwnTeam", DataType::Boolean, false),
+        Field::new("guessLat", DataType::Float64, false),
+        Field::new("guessLng", DataType::Float64, false),
+        string("guessCountryCode", true),
+        Field::new("distance", DataType::Float64, false),
+        Field::new("score", DataType::Int32, false),
+        Field::new("time", DataType::Int32, true),
+        string("date", false),
+        Field::new("isTeamsBest", DataType::Boolean, false)
+    ]))
+}
+
+fn get_record_batch(rows: &[ExportRow], schema: SchemaRef) -> Result<RecordBatch, Error> {
+    let strings = |value: fn(&ExportRow) -> Option<&str>| -> ArrayRef {
+        Arc::new(rows.iter().map(value).collect::<StringArray>())
+    };
+    let floats = |value: fn(&ExportRow) -> f64| -> ArrayRef {
+        Arc::new(rows.iter().map(value).collect::<Float64Array>())
+    };
+    let bools = |value: fn(&ExportRow) -> bool| -> ArrayRef {
+        Arc::new(rows.iter().map(|row| Some(value(row))).collect::<BooleanArray>())
+    };
+
+    let columns = vec![
+        strings(|row| Some(&row.game_id)),
+        strings(|row| Some(row.game_type)),
+        strings(|row| row.team_game_mode.as_deref()),
+        strings(|row| Some(&row.geo_mode)),
+        strings(|row| Some(&row.map_id)),
+        strings(|row| row.map_name.as_deref()),
+        strings(|row| Some(&row.start_time)),
+        Arc::new(rows.iter().map(|row| row.round_number).collect::<Int32Array>()) as ArrayRef,
+        strings(|row| Some(&row.pano_id)),
+        floats(|row| row.lat),
+        floats(|row| row.lng),
+        strings(|row| Some(&row.country_code)),
+        strings(|row| row.subdivision_code.as_deref()),
+        strings(|row| Some(&row.team_id)),
+        strings(|row| row.player_id.as_deref()),
+        bools(|row| row.is_own_team),
+        floats(|row| row.guess_lat),
+        floats(|row| row.guess_lng),
+        strings(|row| row.guess_country_code.as_deref()),
+        floats(|row| row.distance),
+        Arc::new(rows.iter().map(|row| row.score).collect::<Int32Array>()),
+        Arc::new(rows.iter().map(|row| row.time).collect::<Int32Array>()),
+        strings(|row| Some(&row.date)),
+        bools(|row| row.is_teams_best)
+    ];
+
+    RecordBatch::try_new(schema, columns).map_err(ErrorInternalServerError)
+}
+
+/// Encodes the rows of the export chunk by chunk, so that only one batch of games is kept in memory.
+enum ExportEncoder {
+    Csv { has_header: bool },
+    Parquet { schema: SchemaRef, writer: Box<ArrowWriter<Vec<u8>>> },
+    Ndjson
+}
+
+impl ExportEncoder {
+    fn new(format: ExportFormat) -> Result<Self, Error> {
+        Ok(match format {
+            ExportFormat::Csv => ExportEncoder::Csv { has_header: false },
+            ExportFormat::Parquet => {
+                let schema = get_export_schema();
+                let writer = ArrowWriter::try_new(Vec::new(), schema.clone(), None).map_err(ErrorInternalServerError)?;
+                ExportEncoder::Parquet { schema, writer: Box::new(writer) }
+            }
+            ExportFormat::Ndjson => ExportEncoder::Ndjson
+        })
+    }
+
+    fn encode(&mut self, rows: &[ExportRow]) -> Result<Bytes, Error> {
+        match self {
+            ExportEncoder::Csv { has_header } => {
+                let mut writer = csv::WriterBuilder::new().has_headers(!*has_header).from_writer(Vec::new());
+
+                for row in rows {
+                    writer.serialize(row).map_err(ErrorInternalServerError)?;
+                }
+
+                // The header is written with the first row, batches without rows do not have one.
+                *has_header |= !rows.is_empty();
+
+                writer.into_inner().map(Bytes::from).map_err(|err| ErrorInternalServerError(err.to_string()))
+            }
+            ExportEncoder::Parquet { schema, writer } => {
+                if !rows.is_empty() {
+                    writer.write(&get_record_batch(rows, schema.clone())?).map_err(ErrorInternalServerError)?;
+                    writer.flush().map_err(ErrorInternalServerError)?;
+                }
+
+                Ok(Bytes::from(std::mem::take(writer.inner_mut())))
+            }
+            ExportEncoder::Ndjson => {
+                let mut buffer = Vec::new();
+
+                for row in rows {
+                    serde_json::to_writer(&mut buffer, row).map_err(ErrorInternalServerError)?;
+                    buffer.push(b'\n');
+                }
+
+                Ok(Bytes::from(buffer))
+            }
+        }
+    }
+
+    /// Returns the trailing bytes of the export, the footer for Parquet.
+    fn finish(&mut self) -> Result<Bytes, Error> {
+        match self {
+            ExportEncoder::Parquet { writer, .. } => {
+                writer.finish().map_err(ErrorInternalServerError)?;
+                Ok(Bytes::from(std::mem::take(writer.inner_mut())))
+            }
+            _ => Ok(Bytes::new())
+        }
+    }
+}
+
+/// Exports every guess of the session player's duels and solo games. Battle royale games are left
+/// out, their guesses have neither a score nor a team.
+#[get("/export")]
+pub async fn export_games(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ExportQuery>,
+    http_request: HttpRequest
+) -> Result<HttpResponse, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let format = query.into_inner().format;
+    let player_id = get_player_id_from_session(&session_id, db.get_ref()).await?;
+    let games = get_export_games(&player_id, db.get_ref()).await?;
+
+    let maps: HashMap<String, map::Model> = Map::find()
+        .filter(map::Column::Id.is_in(games.iter().map(|game| game.map_id.clone()).collect::<HashSet<_>>()))
+        .all(db.get_ref())
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|map| (map.id.clone(), map))
+        .collect();
+
+    let mut encoder = ExportEncoder::new(format)?;
+
+    let stream = try_stream! {
+        for games in games.chunks(GAME_BATCH_SIZE) {
+            let rows = get_export_rows(games, &maps, db.get_ref()).await?;
+            yield encoder.encode(&rows)?;
+        }
+
+        yield encoder.finish()?;
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"export.{}\"", format.extension())))
+        .streaming::<_, Error>(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export_row() -> ExportRow {
+        ExportRow {
+            game_id: String::from("game"),
+            game_type: "Duels",
+            team_game_mode: Some(String::from("Duels")),
+            geo_mode: String::from("Moving"),
+            map_id: String::from("world"),
+            map_name: Some(String::from("A Diverse World")),
+            start_time: String::from("2024-05-01T12:00:00Z"),
+            round_number: 1,
+            pano_id: String::from("pano"),
+            lat: 48.1,
+            lng: 11.5,
+            country_code: String::from("DE"),
+            subdivision_code: Some(String::from("DE-BY")),
+            team_id: String::from("player"),
+            player_id: Some(String::from("player")),
+            is_own_team: true,
+            guess_lat: 48.2,
+            guess_lng: 11.6,
+            guess_country_code: Some(String::from("DE")),
+            distance: 13_000.0,
+            score: 4800,
+            time: Some(12),
+            date: String::from("2024-05-01T12:00:12Z"),
+            is_teams_best: true
+        }
+    }
+
+    #[test]
+    fn writes_csv_header_after_empty_batches() {
+        let mut encoder = ExportEncoder::new(ExportFormat::Csv).unwrap();
+
+        assert!(encoder.encode(&[]).unwrap().is_empty());
+
+        let first = String::from_utf8(encoder.encode(&[export_row()]).unwrap().to_vec()).unwrap();
+        assert!(first.starts_with("gameId,"));
+        assert_eq!(first.lines().count(), 2);
+
+        let second = String::from_utf8(encoder.encode(&[export_row()]).unwrap().to_vec()).unwrap();
+        assert_eq!(second.lines().count(), 1);
+        assert!(second.starts_with("game,"));
+    }
+}
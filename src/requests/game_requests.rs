@@ -0,0 +1,781 @@
+use crate::entities::prelude::{CompTeam, DuelsGame, DuelsRound, FunTeam, Guess, Location, Map, Player, SoloGame, SoloRound};
+use crate::entities::{comp_team, duels_game, duels_round, fun_team, guess, location, map, player, round_result, solo_game, solo_round};
+use crate::geo_guessr::TeamGameMode;
+use crate::login::{get_player_id_from_session, get_user_from_session};
+use crate::requests::{get_player_team_ids, parse_date};
+use crate::requests::guess_order_requests::get_guess_order;
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::{Alias, Expr, Func, SimpleExpr};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, LoaderTrait, Order, QueryFilter, QueryOrder, QuerySelect};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamRoundResult {
+    team_id: String,
+    score: i32,
+    health_before: i32,
+    health_after: i32,
+    /// The guess that counted for the team's score.
+    best_guess_id: Option<String>,
+    /// Negative on healing rounds.
+    damage_taken: i32,
+    damage_dealt: i32
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RoundLocation {
+    pano_id: String,
+    lat: f64,
+    lng: f64,
+    heading: f64,
+    pitch: f64,
+    zoom: f64,
+    country_code: String,
+    subdivision_code: Option<String>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RoundGuess {
+    team_id: String,
+    /// `None` for guesses stored before the player of a guess was recorded.
+    player_id: Option<String>,
+    lat: f64,
+    lng: f64,
+    score: i32,
+    distance: f64,
+    time: Option<i32>,
+    date: String,
+    country_code: Option<String>,
+    subdivision_code: Option<String>,
+    is_teams_best: bool,
+    /// `None` for guesses whose game has not been rescored yet.
+    is_score_derived: Option<bool>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GameRound {
+    round_number: i32,
+    location: Option<RoundLocation>,
+    guesses: Vec<RoundGuess>,
+    /// Only set for duels games.
+    multiplier: Option<f64>,
+    damage_multiplier: Option<f64>,
+    is_healing_round: Option<bool>,
+    /// Team with the earliest guess and the seconds until the other team guessed, only set for duels games.
+    first_guess_team_id: Option<String>,
+    first_guess_gap: Option<f64>,
+    results: Vec<TeamRoundResult>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GameTeam {
+    team_id: String,
+    name: Option<String>,
+    health: Option<i32>,
+    rating_before: Option<i32>,
+    rating_after: Option<i32>,
+    players: Vec<player::Model>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum GameType {
+    Duels,
+    Solo
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GameResponse {
+    id: String,
+    game_type: GameType,
+    team_game_mode: Option<String>,
+    geo_mode: String,
+    start_time: String,
+    map: Option<map::Model>,
+    teams: Vec<GameTeam>,
+    rounds: Vec<GameRound>
+}
+
+fn get_damage_taken(round_result: &round_result::Model) -> i32 {
+    round_result.health_before - round_result.health_after
+}
+
+fn get_team_round_results(mut round_results: Vec<round_result::Model>, team_id1: &str) -> Vec<TeamRoundResult> {
+    round_results.sort_by_key(|result| result.team_id != team_id1);
+
+    round_results
+        .iter()
+        .map(|result| {
+            let damage_dealt = round_results
+                .iter()
+                .filter(|enemy_result| enemy_result.team_id != result.team_id)
+                .map(get_damage_taken)
+                .sum();
+
+            TeamRoundResult {
+                team_id: result.team_id.clone(),
+                score: result.score,
+                health_before: result.health_before,
+                health_after: result.health_after,
+                best_guess_id: result.best_guess_id.clone(),
+                damage_taken: get_damage_taken(result),
+                damage_dealt
+            }
+        })
+        .collect()
+}
+
+fn get_round_location(location: location::Model) -> RoundLocation {
+    RoundLocation {
+        pano_id: location.id,
+        lat: location.lat,
+        lng: location.lng,
+        heading: location.heading,
+        pitch: location.pitch,
+        zoom: location.zoom,
+        country_code: location.country_code,
+        subdivision_code: location.subdivision_code
+    }
+}
+
+fn get_round_guesses(mut guesses: Vec<guess::Model>, team_id1: &str) -> Vec<RoundGuess> {
+    guesses.sort_by(|a, b| (a.team_id != team_id1).cmp(&(b.team_id != team_id1)).then_with(|| a.date.cmp(&b.date)));
+
+    guesses
+        .into_iter()
+        .map(|guess| RoundGuess {
+            team_id: guess.team_id,
+            player_id: guess.player_id,
+            lat: guess.lat,
+            lng: guess.lng,
+            score: guess.score,
+            distance: guess.distance,
+            time: guess.time,
+            date: guess.date,
+            country_code: guess.country_code,
+            subdivision_code: guess.subdivision_code,
+            is_teams_best: guess.is_teams_best,
+            is_score_derived: guess.is_score_derived
+        })
+        .collect()
+}
+
+pub async fn get_players(player_ids: &[String], db: &DatabaseConnection) -> Result<HashMap<String, player::Model>, DbErr> {
+    let players = Player::find()
+        .filter(player::Column::Id.is_in(player_ids))
+        .all(db)
+        .await?;
+
+    Ok(players.into_iter().map(|player| (player.id.clone(), player)).collect())
+}
+
+/// Returns the team name and player ids of a duels team.
+pub async fn get_team_members(team_id: &str, team_game_mode: &TeamGameMode, db: &DatabaseConnection) -> Result<(Option<String>, Vec<String>), DbErr> {
+    match team_game_mode {
+        TeamGameMode::Duels | TeamGameMode::DuelsRanked => Ok((None, vec![String::from(team_id)])),
+        TeamGameMode::TeamDuels | TeamGameMode::TeamDuelsRanked => {
+            match CompTeam::find_by_id(team_id).one(db).await? {
+                Some(team) => Ok((Some(team.name), vec![team.player_id1, team.player_id2])),
+                None => Ok((None, Vec::new()))
+            }
+        }
+        TeamGameMode::TeamFun => {
+            match FunTeam::find_by_id(team_id).one(db).await? {
+                Some(team) => Ok((None, team.player_ids)),
+                None => Ok((None, Vec::new()))
+            }
+        }
+    }
+}
+
+/// Returns the team name and player ids of several duels teams, keyed by team id. The competitive
+/// and fun teams are loaded with one query each.
+pub async fn get_teams_members(
+    teams: &[(String, TeamGameMode)],
+    db: &DatabaseConnection
+) -> Result<HashMap<String, (Option<String>, Vec<String>)>, DbErr> {
+    let mut members = HashMap::new();
+    let mut comp_team_ids = Vec::new();
+    let mut fun_team_ids = Vec::new();
+
+    for (team_id, team_game_mode) in teams {
+        match team_game_mode {
+            TeamGameMode::Duels | TeamGameMode::DuelsRanked => {
+                members.insert(team_id.clone(), (None, vec![team_id.clone()]));
+            }
+            TeamGameMode::TeamDuels | TeamGameMode::TeamDuelsRanked => comp_team_ids.push(team_id.clone()),
+            TeamGameMode::TeamFun => fun_team_ids.push(team_id.clone())
+        }
+    }
+
+    if !comp_team_ids.is_empty() {
+        for team in CompTeam::find().filter(comp_team::Column::TeamId.is_in(comp_team_ids)).all(db).await? {
+            members.insert(team.team_id, (Some(team.name), vec![team.player_id1, team.player_id2]));
+        }
+    }
+
+    if !fun_team_ids.is_empty() {
+        for team in FunTeam::find().filter(fun_team::Column::TeamId.is_in(fun_team_ids)).all(db).await? {
+            members.insert(team.team_id, (None, team.player_ids));
+        }
+    }
+
+    Ok(members)
+}
+
+async fn get_duels_game_response(game: duels_game::Model, db: &DatabaseConnection) -> Result<GameResponse, DbErr> {
+    let team_game_mode = TeamGameMode::from_str(&game.team_game_mode).map_err(|err| DbErr::Custom(err.to_string()))?;
+
+    let rounds = DuelsRound::find()
+        .filter(duels_round::Column::GameId.eq(&game.id))
+        .order_by_asc(duels_round::Column::RoundNumber)
+        .all(db)
+        .await?;
+
+    let rounds_results = rounds.load_many(round_result::Entity, db).await?;
+    let rounds_guesses = rounds.load_many(guess::Entity, db).await?;
+    let locations = rounds.load_one(location::Entity, db).await?;
+
+    let rounds = rounds
+        .into_iter()
+        .zip(rounds_results)
+        .zip(rounds_guesses)
+        .zip(locations)
+        .map(|(((round, round_results), guesses), location)| {
+            let (first_guess_team_id, first_guess_gap) = match get_guess_order(&guesses.iter().collect::<Vec<_>>()) {
+                Some(guess_order) => (Some(String::from(guess_order.first_team_id)), guess_order.gap),
+                None => (None, None)
+            };
+
+            GameRound {
+                round_number: round.round_number,
+                location: location.map(get_round_location),
+                guesses: get_round_guesses(guesses, &game.team_id1),
+                multiplier: Some(round.multiplier),
+                damage_multiplier: Some(round.damage_multiplier),
+                is_healing_round: Some(round.is_healing_round),
+                first_guess_team_id,
+                first_guess_gap,
+                results: get_team_round_results(round_results, &game.team_id1)
+            }
+        })
+        .collect();
+
+    let team_values = [
+        (game.team_id1, game.health_team1, game.rating_before_team1, game.rating_after_team1),
+        (game.team_id2, game.health_team2, game.rating_before_team2, game.rating_after_team2)
+    ];
+
+    let mut teams = Vec::with_capacity(team_values.len());
+
+    for (team_id, health, rating_before, rating_after) in team_values {
+        let (name, player_ids) = get_team_members(&team_id, &team_game_mode, db).await?;
+        let mut players = get_players(&player_ids, db).await?;
+
+        teams.push(GameTeam {
+            team_id,
+            name,
+            health: Some(health),
+            rating_before,
+            rating_after,
+            players: player_ids.iter().filter_map(|player_id| players.remove(player_id)).collect()
+        });
+    }
+
+    Ok(GameResponse {
+        id: game.id,
+        game_type: GameType::Duels,
+        team_game_mode: Some(game.team_game_mode),
+        geo_mode: game.geo_mode,
+        start_time: game.start_time,
+        map: Map::find_by_id(game.map_id).one(db).await?,
+        teams,
+        rounds
+    })
+}
+
+async fn get_solo_game_response(game: solo_game::Model, db: &DatabaseConnection) -> Result<GameResponse, DbErr> {
+    let rounds = SoloRound::find()
+        .filter(solo_round::Column::GameId.eq(&game.id))
+        .order_by_asc(solo_round::Column::RoundNumber)
+        .all(db)
+        .await?;
+
+    let locations: HashMap<String, location::Model> = Location::find()
+        .filter(location::Column::Id.is_in(rounds.iter().map(|round| round.location_id.clone())))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|location| (location.id.clone(), location))
+        .collect();
+
+    let mut rounds_guesses: HashMap<String, Vec<guess::Model>> = HashMap::new();
+
+    for guess in Guess::find()
+        .filter(guess::Column::GameId.eq(&game.id))
+        .all(db)
+        .await?
+    {
+        rounds_guesses.entry(guess.round_id.clone()).or_default().push(guess);
+    }
+
+    let rounds = rounds
+        .into_iter()
+        .map(|round| GameRound {
+            round_number: round.round_number,
+            location: locations.get(&round.location_id).cloned().map(get_round_location),
+            guesses: get_round_guesses(rounds_guesses.remove(&round.id).unwrap_or_default(), &game.player_id),
+            multiplier: None,
+            damage_multiplier: None,
+            is_healing_round: None,
+            first_guess_team_id: None,
+            first_guess_gap: None,
+            results: Vec::new()
+        })
+        .collect();
+
+    let players = Player::find_by_id(&game.player_id)
+        .one(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let team = GameTeam {
+        team_id: game.player_id,
+        name: None,
+        health: None,
+        rating_before: None,
+        rating_after: None,
+        players
+    };
+
+    Ok(GameResponse {
+        id: game.id,
+        game_type: GameType::Solo,
+        team_game_mode: None,
+        geo_mode: game.geo_mode,
+        start_time: game.start_time,
+        map: Map::find_by_id(game.map_id).one(db).await?,
+        teams: vec![team],
+        rounds
+    })
+}
+
+#[get("/games/{game_id}")]
+pub async fn get_game(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let game_id = path.into_inner();
+    get_user_from_session(&session_id, db).await?;
+
+    if let Some(game) = DuelsGame::find_by_id(&game_id).one(db).await.map_err(ErrorInternalServerError)? {
+        let response = get_duels_game_response(game, db).await.map_err(ErrorInternalServerError)?;
+        return Ok(HttpResponse::Ok().json(response));
+    }
+
+    if let Some(game) = SoloGame::find_by_id(&game_id).one(db).await.map_err(ErrorInternalServerError)? {
+        let response = get_solo_game_response(game, db).await.map_err(ErrorInternalServerError)?;
+        return Ok(HttpResponse::Ok().json(response));
+    }
+
+    Err(ErrorNotFound("Game not found!"))
+}
+
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum GameResult {
+    Win,
+    Loss,
+    Draw
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum GameSort {
+    #[default]
+    Date,
+    RatingChange,
+    FinalHealth
+}
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum SortOrder {
+    Asc,
+    #[default]
+    Desc
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GamesQuery {
+    team_game_mode: Option<String>,
+    geo_mode: Option<String>,
+    map_id: Option<String>,
+    /// Player or team id of the opponent.
+    opponent_id: Option<String>,
+    result: Option<GameResult>,
+    rated: Option<bool>,
+    /// RFC 3339 dates, both inclusive.
+    from: Option<String>,
+    to: Option<String>,
+    #[serde(default)]
+    sort: GameSort,
+    #[serde(default)]
+    order: SortOrder,
+    /// Id of the last game of the previous page.
+    cursor: Option<String>,
+    limit: Option<usize>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GameSummary {
+    id: String,
+    team_game_mode: String,
+    geo_mode: String,
+    start_time: String,
+    map_id: String,
+    map_name: Option<String>,
+    team_id: String,
+    opponent_team_id: String,
+    opponent_names: Vec<String>,
+    result: GameResult,
+    rounds: i64,
+    final_health: i32,
+    opponent_final_health: i32,
+    rating_before: Option<i32>,
+    rating_after: Option<i32>,
+    rating_change: Option<i32>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GamesResponse {
+    games: Vec<GameSummary>,
+    next_cursor: Option<String>
+}
+
+/// A duels game seen from the side of the session player.
+pub struct PlayerGame {
+    pub game: duels_game::Model,
+    pub team_id: String,
+    pub opponent_team_id: String,
+    pub final_health: i32,
+    pub opponent_final_health: i32,
+    pub rating_before: Option<i32>,
+    pub rating_after: Option<i32>,
+    pub opponent_rating_before: Option<i32>,
+    pub start_time: DateTime<Utc>
+}
+
+impl PlayerGame {
+    pub fn new(game: duels_game::Model, team_ids: &HashSet<String>) -> Result<PlayerGame, Error> {
+        let start_time = game.start_time.parse().map_err(ErrorInternalServerError)?;
+
+        let player_game = if team_ids.contains(&game.team_id1) {
+            PlayerGame {
+                team_id: game.team_id1.clone(),
+                opponent_team_id: game.team_id2.clone(),
+                final_health: game.health_team1,
+                opponent_final_health: game.health_team2,
+                rating_before: game.rating_before_team1,
+                rating_after: game.rating_after_team1,
+                opponent_rating_before: game.rating_before_team2,
+                start_time,
+                game
+            }
+        } else {
+            PlayerGame {
+                team_id: game.team_id2.clone(),
+                opponent_team_id: game.team_id1.clone(),
+                final_health: game.health_team2,
+                opponent_final_health: game.health_team1,
+                rating_before: game.rating_before_team2,
+                rating_after: game.rating_after_team2,
+                opponent_rating_before: game.rating_before_team1,
+                start_time,
+                game
+            }
+        };
+
+        Ok(player_game)
+    }
+
+    pub fn result(&self) -> GameResult {
+        match self.final_health.cmp(&self.opponent_final_health) {
+            Ordering::Greater => GameResult::Win,
+            Ordering::Less => GameResult::Loss,
+            Ordering::Equal => GameResult::Draw
+        }
+    }
+
+    pub fn rating_change(&self) -> Option<i32> {
+        Some(self.rating_after? - self.rating_before?)
+    }
+}
+
+/// Sorts games without a rating change before all others, like `None` sorts before `Some`.
+const MISSING_RATING_CHANGE: i32 = i32::MIN;
+
+/// Picks the value of the session player's team in a game.
+pub fn player_team_expr(team_ids: &HashSet<String>, team1_column: duels_game::Column, team2_column: duels_game::Column) -> SimpleExpr {
+    Expr::case(duels_game::Column::TeamId1.is_in(team_ids.iter().cloned()), Expr::col((duels_game::Entity, team1_column)))
+        .finally(Expr::col((duels_game::Entity, team2_column)))
+        .into()
+}
+
+/// Picks the value of the opponent's team in a game.
+pub fn opponent_team_expr(team_ids: &HashSet<String>, team1_column: duels_game::Column, team2_column: duels_game::Column) -> SimpleExpr {
+    player_team_expr(team_ids, team2_column, team1_column)
+}
+
+pub fn start_time_expr(start_time: SimpleExpr) -> SimpleExpr {
+    Func::cast_as(start_time, Alias::new("timestamptz")).into()
+}
+
+fn sort_expr(sort: GameSort, team_ids: &HashSet<String>) -> Option<SimpleExpr> {
+    match sort {
+        GameSort::Date => None,
+        GameSort::RatingChange => Some(
+            Func::coalesce([
+                player_team_expr(team_ids, duels_game::Column::RatingAfterTeam1, duels_game::Column::RatingAfterTeam2)
+                    .sub(player_team_expr(team_ids, duels_game::Column::RatingBeforeTeam1, duels_game::Column::RatingBeforeTeam2)),
+                Expr::val(MISSING_RATING_CHANGE).into()
+            ])
+            .into()
+        ),
+        GameSort::FinalHealth => Some(player_team_expr(team_ids, duels_game::Column::HealthTeam1, duels_game::Column::HealthTeam2))
+    }
+}
+
+fn sort_value(sort: GameSort, game: &PlayerGame) -> Option<SimpleExpr> {
+    match sort {
+        GameSort::Date => None,
+        GameSort::RatingChange => Some(Expr::val(game.rating_change().unwrap_or(MISSING_RATING_CHANGE)).into()),
+        GameSort::FinalHealth => Some(Expr::val(game.final_health).into())
+    }
+}
+
+/// Games that come after the cursor game in the sort order, compared by the sort key,
+/// then the start time and then the id.
+fn after_cursor_condition(cursor: &PlayerGame, sort: GameSort, order: SortOrder, team_ids: &HashSet<String>) -> Condition {
+    let is_after = |expr: SimpleExpr, value: SimpleExpr| match order {
+        SortOrder::Asc => Expr::expr(expr).gt(value),
+        SortOrder::Desc => Expr::expr(expr).lt(value)
+    };
+
+    let start_time = start_time_expr(Expr::col(duels_game::Column::StartTime).into());
+    let cursor_start_time = start_time_expr(Expr::val(cursor.game.start_time.clone()).into());
+
+    let same_start_time = Condition::all()
+        .add(Expr::expr(start_time.clone()).eq(cursor_start_time.clone()))
+        .add(is_after(Expr::col(duels_game::Column::Id).into(), Expr::val(cursor.game.id.clone()).into()));
+
+    let same_key = Condition::any()
+        .add(is_after(start_time, cursor_start_time))
+        .add(same_start_time);
+
+    match (sort_expr(sort, team_ids), sort_value(sort, cursor)) {
+        (Some(key), Some(cursor_key)) => Condition::any()
+            .add(is_after(key.clone(), cursor_key.clone()))
+            .add(Condition::all().add(Expr::expr(key).eq(cursor_key)).add(same_key)),
+        _ => same_key
+    }
+}
+
+#[get("/games")]
+pub async fn get_games(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<GamesQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let query = query.into_inner();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+    let team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+
+    let from = parse_date(&query.from)?;
+    let to = parse_date(&query.to)?;
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    let opponent_team_ids = match &query.opponent_id {
+        Some(opponent_id) => {
+            let mut opponent_team_ids = get_player_team_ids(opponent_id, db).await.map_err(ErrorInternalServerError)?;
+            opponent_team_ids.insert(opponent_id.clone());
+            Some(opponent_team_ids)
+        }
+        None => None
+    };
+
+    let mut game_query = DuelsGame::find()
+        .filter(duels_game::Column::TeamId1.is_in(&team_ids).or(duels_game::Column::TeamId2.is_in(&team_ids)));
+
+    if let Some(team_game_mode) = &query.team_game_mode {
+        game_query = game_query.filter(duels_game::Column::TeamGameMode.eq(team_game_mode));
+    }
+    if let Some(geo_mode) = &query.geo_mode {
+        game_query = game_query.filter(duels_game::Column::GeoMode.eq(geo_mode));
+    }
+    if let Some(map_id) = &query.map_id {
+        game_query = game_query.filter(duels_game::Column::MapId.eq(map_id));
+    }
+    if let Some(opponent_team_ids) = &opponent_team_ids {
+        game_query = game_query.filter(
+            Expr::expr(opponent_team_expr(&team_ids, duels_game::Column::TeamId1, duels_game::Column::TeamId2))
+                .is_in(opponent_team_ids.iter().cloned())
+        );
+    }
+    if let Some(result) = query.result {
+        let final_health = Expr::expr(player_team_expr(&team_ids, duels_game::Column::HealthTeam1, duels_game::Column::HealthTeam2));
+        let opponent_final_health = opponent_team_expr(&team_ids, duels_game::Column::HealthTeam1, duels_game::Column::HealthTeam2);
+
+        game_query = game_query.filter(match result {
+            GameResult::Win => final_health.gt(opponent_final_health),
+            GameResult::Loss => final_health.lt(opponent_final_health),
+            GameResult::Draw => final_health.eq(opponent_final_health)
+        });
+    }
+    if let Some(rated) = query.rated {
+        let rated_game_modes = [TeamGameMode::DuelsRanked.to_string(), TeamGameMode::TeamDuelsRanked.to_string()];
+
+        game_query = game_query.filter(if rated {
+            duels_game::Column::TeamGameMode.is_in(rated_game_modes)
+        } else {
+            duels_game::Column::TeamGameMode.is_not_in(rated_game_modes)
+        });
+    }
+    if let Some(from) = from {
+        game_query = game_query.filter(Expr::expr(start_time_expr(Expr::col(duels_game::Column::StartTime).into())).gte(from));
+    }
+    if let Some(to) = to {
+        game_query = game_query.filter(Expr::expr(start_time_expr(Expr::col(duels_game::Column::StartTime).into())).lte(to));
+    }
+    if let Some(cursor) = &query.cursor {
+        let cursor_game = DuelsGame::find_by_id(cursor)
+            .one(db)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .filter(|game| team_ids.contains(&game.team_id1) || team_ids.contains(&game.team_id2))
+            .ok_or_else(|| ErrorBadRequest("Invalid cursor!"))?;
+
+        game_query = game_query.filter(after_cursor_condition(&PlayerGame::new(cursor_game, &team_ids)?, query.sort, query.order, &team_ids));
+    }
+
+    let order = match query.order {
+        SortOrder::Asc => Order::Asc,
+        SortOrder::Desc => Order::Desc
+    };
+
+    if let Some(key) = sort_expr(query.sort, &team_ids) {
+        game_query = game_query.order_by(key, order.clone());
+    }
+
+    let mut page: Vec<PlayerGame> = game_query
+        .order_by(start_time_expr(Expr::col(duels_game::Column::StartTime).into()), order.clone())
+        .order_by(duels_game::Column::Id, order)
+        .limit(limit as u64 + 1)
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| PlayerGame::new(game, &team_ids))
+        .collect::<Result<_, _>>()?;
+
+    let has_more = page.len() > limit;
+    page.truncate(limit);
+    let next_cursor = if has_more { page.last().map(|game| game.game.id.clone()) } else { None };
+
+    let round_counts: HashMap<String, i64> = DuelsRound::find()
+        .select_only()
+        .column(duels_round::Column::GameId)
+        .column_as(duels_round::Column::Id.count(), "count")
+        .filter(duels_round::Column::GameId.is_in(page.iter().map(|game| game.game.id.clone())))
+        .group_by(duels_round::Column::GameId)
+        .into_tuple::<(String, i64)>()
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .collect();
+
+    let map_names: HashMap<String, String> = Map::find()
+        .filter(map::Column::Id.is_in(page.iter().map(|game| game.game.map_id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|map| (map.id, map.name))
+        .collect();
+
+    let mut summaries = Vec::with_capacity(page.len());
+
+    for game in page {
+        let team_game_mode = TeamGameMode::from_str(&game.game.team_game_mode).map_err(ErrorInternalServerError)?;
+        let (_, opponent_ids) = get_team_members(&game.opponent_team_id, &team_game_mode, db)
+            .await
+            .map_err(ErrorInternalServerError)?;
+        let mut opponents = get_players(&opponent_ids, db).await.map_err(ErrorInternalServerError)?;
+
+        summaries.push(GameSummary {
+            result: game.result(),
+            rating_change: game.rating_change(),
+            rounds: round_counts.get(&game.game.id).copied().unwrap_or_default(),
+            map_name: map_names.get(&game.game.map_id).cloned(),
+            opponent_names: opponent_ids
+                .iter()
+                .filter_map(|opponent_id| opponents.remove(opponent_id))
+                .map(|opponent| opponent.name)
+                .collect(),
+            id: game.game.id,
+            team_game_mode: game.game.team_game_mode,
+            geo_mode: game.game.geo_mode,
+            start_time: game.game.start_time,
+            map_id: game.game.map_id,
+            team_id: game.team_id,
+            opponent_team_id: game.opponent_team_id,
+            final_health: game.final_health,
+            opponent_final_health: game.opponent_final_health,
+            rating_before: game.rating_before,
+            rating_after: game.rating_after
+        });
+    }
+
+    let response = GamesResponse {
+        games: summaries,
+        next_cursor
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -3,15 +3,22 @@ use crate::entities::player::Model as PlayerModel;
 use crate::entities::prelude::{CompTeam, DuelsGame, Guess};
 use crate::entities::{comp_team, duels_game, guess};
 use crate::geo_guessr::TeamGameMode;
-use crate::login::get_player_from_session;
+use crate::login::{get_player_from_session, get_public_player};
 use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
 use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
 use chrono::{DateTime, Utc};
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeneralStatsQuery {
+    #[serde(default)]
+    personal: bool
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct HomePageResponse {
@@ -92,20 +99,8 @@ async fn get_processed_stats(guesses: Vec<GuessModel>, guess_id_to_game_mode: &H
     Ok(stats)
 }
 
-#[get("/stats")]
-pub async fn get_general_stats(
-    db: web::Data<DatabaseConnection>,
-    http_request: HttpRequest
-) -> Result<impl Responder, Error> {
-    let session_id = match http_request.cookie("sessionId") {
-        Some(cookie) => {
-            String::from(cookie.value())
-        },
-        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
-    };
-    
-    let db = db.get_ref();
-    let player = get_player_from_session(&session_id, db).await?;
+/// With `personal` set, the player's own guesses are used instead of their team's best guesses.
+async fn get_general_stats_response(player: PlayerModel, personal: bool, db: &DatabaseConnection) -> Result<HttpResponse, Error> {
     let mut team_ids: HashSet<String> = [player.id.clone()].into_iter().collect();
     
     match CompTeam::find()
@@ -120,7 +115,7 @@ pub async fn get_general_stats(
     let games_guesses = match DuelsGame::find()
         .filter(duels_game::Column::TeamId1.is_in(&team_ids).or(duels_game::Column::TeamId2.is_in(&team_ids)))
         .find_with_related(Guess)
-        .filter(guess::Column::IsTeamsBest.eq(true))
+        .filter(guess::Column::IsTeamsBest.eq(true).or(guess::Column::PlayerId.eq(&player.id)))
         .all(db)
         .await 
     {
@@ -146,9 +141,17 @@ pub async fn get_general_stats(
     for (game, guesses) in games_guesses {
         for guess in guesses {
             if team_ids.contains(&guess.team_id) {
-                guess_id_to_game_mode.insert(guess.id.clone(), game.team_game_mode.clone());
-                player_guesses.push(guess);
-            } else {
+                let is_included = if personal {
+                    guess.player_id.as_ref() == Some(&player.id)
+                } else {
+                    guess.is_teams_best
+                };
+
+                if is_included {
+                    guess_id_to_game_mode.insert(guess.id.clone(), game.team_game_mode.clone());
+                    player_guesses.push(guess);
+                }
+            } else if guess.is_teams_best {
                 guess_id_to_game_mode.insert(guess.id.clone(), game.team_game_mode.clone());
                 enemy_guesses.push(guess);
             }
@@ -170,4 +173,35 @@ pub async fn get_general_stats(
     };
     
     Ok(HttpResponse::Ok().json(response))
-}
\ No newline at end of file
+}
+
+#[get("/stats")]
+pub async fn get_general_stats(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<GeneralStatsQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+    
+    let db = db.get_ref();
+    let player = get_player_from_session(&session_id, db).await?;
+
+    get_general_stats_response(player, query.personal, db).await
+}
+
+#[get("/players/{player_id}/stats")]
+pub async fn get_public_general_stats(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    query: web::Query<GeneralStatsQuery>
+) -> Result<impl Responder, Error> {
+    let db = db.get_ref();
+    let player = get_public_player(&path.into_inner(), db).await?;
+
+    get_general_stats_response(player, query.personal, db).await
+}
@@ -1,16 +1,26 @@
+use actix_web::error::{ErrorBadGateway, ErrorInternalServerError, ErrorServiceUnavailable};
+use actix_web::Error;
 use chrono::{DateTime, Duration, Utc};
+use dotenv::dotenv;
 use lazy_static::lazy_static;
-use reqwest::Client;
+use log::{error, warn};
+use reqwest::header::COOKIE;
+use reqwest::{Client, Response, StatusCode};
 use serde::Serialize;
-use std::error::Error;
+use std::env;
 use std::fmt::Write as FmtWrite;
+use std::future::Future;
 use std::ops::Add;
-use log::error;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::Mutex;
 
+const LOGIN_ATTEMPTS: u32 = 4;
+const LOGIN_BACKOFF_BASE: std::time::Duration = std::time::Duration::from_millis(500);
+const COOKIE_EXPIRE_MARGIN: Duration = Duration::seconds(15);
+const DEFAULT_GUEST_NICK: &str = "geo_stats";
+
 lazy_static! {
-    static ref COOKIES: Mutex<String> = Mutex::new(String::new());
-    static ref COOKIE_EXPIRE_DATE: Mutex<DateTime<Utc>> = Mutex::new(Utc::now());
+    static ref COOKIE_MANAGER: CookieManager = CookieManager::from_env();
 }
 
 #[derive(Serialize)]
@@ -19,44 +29,211 @@ struct GuestRequest {
     nick: String
 }
 
-pub async fn get_cookies() -> String {
-    let mut expire = COOKIE_EXPIRE_DATE.lock().await;
+struct CookieState {
+    cookies: String,
+    expire_date: DateTime<Utc>
+}
+
+/// Logs in a guest account, returns its cookies and when they expire.
+pub trait GuestLogin {
+    fn login(&self, nick: &str) -> impl Future<Output = Result<(String, DateTime<Utc>), Box<dyn std::error::Error>>>;
+}
+
+/// Logs in through GeoGuessr's guest user endpoint.
+pub struct GeoGuessrLogin;
+
+impl GuestLogin for GeoGuessrLogin {
+    async fn login(&self, nick: &str) -> Result<(String, DateTime<Utc>), Box<dyn std::error::Error>> {
+        login(nick).await
+    }
+}
+
+/// Keeps the cookies used for authenticated GeoGuessr requests.
+///
+/// If `GEO_NCFA_COOKIE` is set, the operator-provided `_ncfa` cookie is used as is.
+/// Otherwise a guest account is logged in, rotating through the nicks in `GEO_GUEST_NICKS`
+/// (comma separated) every time the cookies have to be renewed.
+pub struct CookieManager<L: GuestLogin = GeoGuessrLogin> {
+    state: Mutex<CookieState>,
+    guest_nicks: Vec<String>,
+    next_guest: AtomicUsize,
+    ncfa_cookie: Option<String>,
+    guest_login: L,
+    login_backoff: std::time::Duration
+}
+
+impl CookieManager {
+    pub fn from_env() -> Self {
+        dotenv().ok();
+
+        let guest_nicks = env::var("GEO_GUEST_NICKS")
+            .map(|nicks| {
+                nicks
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|nick| !nick.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ncfa_cookie = env::var("GEO_NCFA_COOKIE")
+            .ok()
+            .filter(|cookie| !cookie.is_empty());
 
-    if *expire < Utc::now() + Duration::seconds(15) {
-        match login().await {
-            Ok(new_expire) => *expire = new_expire,
-            Err(err) => {
-                error!("Geo guest login failed! Error: {}", err);
-                std::process::exit(1);
+        CookieManager::new(guest_nicks, ncfa_cookie, GeoGuessrLogin)
+    }
+}
+
+impl<L: GuestLogin> CookieManager<L> {
+    pub fn new(guest_nicks: Vec<String>, ncfa_cookie: Option<String>, guest_login: L) -> Self {
+        let guest_nicks = if guest_nicks.is_empty() {
+            vec![String::from(DEFAULT_GUEST_NICK)]
+        } else {
+            guest_nicks
+        };
+
+        CookieManager {
+            state: Mutex::new(CookieState {
+                cookies: String::new(),
+                expire_date: Utc::now()
+            }),
+            guest_nicks,
+            next_guest: AtomicUsize::new(0),
+            ncfa_cookie,
+            guest_login,
+            login_backoff: LOGIN_BACKOFF_BASE
+        }
+    }
+
+    /// Returns the current cookies, logging in a guest if they are missing or about to expire.
+    /// Failed logins are retried with the next nick, the state is not locked during the backoff
+    /// so that other requests are not held up by a login outage.
+    pub async fn get_cookies(&self) -> Result<String, Error> {
+        if let Some(ncfa_cookie) = &self.ncfa_cookie {
+            return Ok(format!("_ncfa={}; ", ncfa_cookie));
+        }
+
+        let mut backoff = self.login_backoff;
+
+        for attempt in 1..=LOGIN_ATTEMPTS {
+            {
+                let mut state = self.state.lock().await;
+
+                if !state.cookies.is_empty() && state.expire_date >= Utc::now() + COOKIE_EXPIRE_MARGIN {
+                    return Ok(state.cookies.clone());
+                }
+
+                let guest_index = self.next_guest.fetch_add(1, Ordering::Relaxed) % self.guest_nicks.len();
+                let nick = &self.guest_nicks[guest_index];
+
+                match self.guest_login.login(nick).await {
+                    Ok((cookies, expire_date)) => {
+                        state.cookies = cookies.clone();
+                        state.expire_date = expire_date;
+                        return Ok(cookies);
+                    }
+                    Err(err) => error!("Geo guest login as {} failed (attempt {}/{})! Error: {}", nick, attempt, LOGIN_ATTEMPTS, err)
+                }
             }
+
+            if attempt < LOGIN_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        Err(ErrorServiceUnavailable("Could not log in to GeoGuessr!"))
+    }
+
+    /// Forces a new guest login on the next call to [`CookieManager::get_cookies`].
+    pub async fn invalidate(&self) {
+        let mut state = self.state.lock().await;
+        state.cookies.clear();
+        state.expire_date = Utc::now();
+    }
+
+    /// Sends a GET request with the current cookies. When GeoGuessr answers with
+    /// `401` or `403` the cookies are renewed and the request is sent once more.
+    pub async fn get(&self, client: &Client, url: &str) -> Result<Response, Error> {
+        self.get_with(url, |cookies| send_get(client, url, cookies)).await
+    }
+
+    /// Like [`CookieManager::get`], with `send` sending the request with the given cookies.
+    /// Returns an error instead of the response if GeoGuessr keeps rejecting the cookies.
+    async fn get_with<F, Fut>(&self, url: &str, send: F) -> Result<Response, Error>
+    where
+        F: Fn(String) -> Fut,
+        Fut: Future<Output = Result<Response, Error>>
+    {
+        let cookies = self.get_cookies().await?;
+        let response = send(cookies).await?;
+
+        if !is_auth_failure(response.status()) {
+            return Ok(response);
+        }
+
+        if self.ncfa_cookie.is_some() {
+            error!("GeoGuessr rejected the configured _ncfa cookie with status {}", response.status());
+            return Err(ErrorBadGateway("GeoGuessr rejected the configured `_ncfa` cookie!"));
+        }
+
+        warn!("GeoGuessr answered {} for {}, renewing guest cookies", response.status(), url);
+        self.invalidate().await;
+
+        let cookies = self.get_cookies().await?;
+        let response = send(cookies).await?;
+
+        if is_auth_failure(response.status()) {
+            error!("GeoGuessr rejected the renewed guest cookies with status {}", response.status());
+            return Err(ErrorBadGateway("GeoGuessr rejected the guest cookies!"));
         }
+
+        Ok(response)
     }
-    
-    drop(expire);
+}
 
-    let cookies = COOKIES.lock().await;
-    cookies.clone()
+fn is_auth_failure(status: StatusCode) -> bool {
+    status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN
 }
 
-async fn login() -> Result<DateTime<Utc>, Box<dyn Error>> {
+async fn send_get(client: &Client, url: &str, cookies: String) -> Result<Response, Error> {
+    client
+        .get(url)
+        .header(COOKIE, cookies)
+        .send()
+        .await
+        .map_err(|err| ErrorInternalServerError(format!("Fetch operation failed! Error: {}", err)))
+}
+
+/// Sends an authenticated GET request to GeoGuessr, see [`CookieManager::get`].
+pub async fn get(client: &Client, url: &str) -> Result<Response, Error> {
+    COOKIE_MANAGER.get(client, url).await
+}
+
+async fn login(nick: &str) -> Result<(String, DateTime<Utc>), Box<dyn std::error::Error>> {
     let client = Client::builder().cookie_store(true).build()?;
 
     let request_body = GuestRequest {
-        nick: String::from("geo_stats")
+        nick: String::from(nick)
     };
 
     let response = client
         .post("https://www.geoguessr.com/api/v4/guest-users")
         .json(&request_body)
         .send()
-        .await?;
+        .await?
+        .error_for_status()?;
 
-    let mut cookies = COOKIES.lock().await;
-    *cookies = response.cookies().fold(String::new(), |mut acc, cookie| {
+    let cookies = response.cookies().fold(String::new(), |mut acc, cookie| {
         write!(&mut acc, "{}={}; ", cookie.name(), cookie.value()).unwrap();
         acc
     });
 
+    if cookies.is_empty() {
+        return Err("guest login returned no cookies".into());
+    }
+
     let mut first_expire_option = None;
 
     for cookie in response.cookies() {
@@ -79,5 +256,137 @@ async fn login() -> Result<DateTime<Utc>, Box<dyn Error>> {
         Utc::now().add(Duration::new(50_000, 0).unwrap())
     };
 
-    Ok(expire)
+    Ok((cookies, expire))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::atomic::AtomicU32;
+
+    /// Answers the logins with the queued results and records the nicks.
+    struct StubLogin {
+        results: std::sync::Mutex<VecDeque<bool>>,
+        nicks: std::sync::Mutex<Vec<String>>
+    }
+
+    impl StubLogin {
+        fn new(results: &[bool]) -> Self {
+            StubLogin {
+                results: std::sync::Mutex::new(results.iter().copied().collect()),
+                nicks: std::sync::Mutex::default()
+            }
+        }
+    }
+
+    impl GuestLogin for StubLogin {
+        async fn login(&self, nick: &str) -> Result<(String, DateTime<Utc>), Box<dyn std::error::Error>> {
+            self.nicks.lock().unwrap().push(String::from(nick));
+
+            if self.results.lock().unwrap().pop_front().unwrap_or(true) {
+                Ok((format!("_ncfa={}; ", nick), Utc::now() + Duration::hours(1)))
+            } else {
+                Err("login failed".into())
+            }
+        }
+    }
+
+    fn cookie_manager(results: &[bool], ncfa_cookie: Option<&str>) -> CookieManager<StubLogin> {
+        let mut cookie_manager = CookieManager::new(
+            vec![String::from("a"), String::from("b")],
+            ncfa_cookie.map(String::from),
+            StubLogin::new(results)
+        );
+        cookie_manager.login_backoff = std::time::Duration::ZERO;
+        cookie_manager
+    }
+
+    fn nicks(cookie_manager: &CookieManager<StubLogin>) -> Vec<String> {
+        cookie_manager.guest_login.nicks.lock().unwrap().clone()
+    }
+
+    fn response(status: StatusCode) -> Response {
+        Response::from(http::Response::builder().status(status.as_u16()).body("").unwrap())
+    }
+
+    #[tokio::test]
+    async fn retries_failed_logins_with_the_next_nick() {
+        let cookie_manager = cookie_manager(&[false, true], None);
+
+        assert_eq!(cookie_manager.get_cookies().await.unwrap(), "_ncfa=b; ");
+        assert_eq!(nicks(&cookie_manager), ["a", "b"]);
+
+        // Valid cookies are reused.
+        cookie_manager.get_cookies().await.unwrap();
+        assert_eq!(nicks(&cookie_manager).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_all_attempts() {
+        let cookie_manager = cookie_manager(&[false; LOGIN_ATTEMPTS as usize], None);
+
+        assert!(cookie_manager.get_cookies().await.is_err());
+        assert_eq!(nicks(&cookie_manager).len(), LOGIN_ATTEMPTS as usize);
+    }
+
+    #[tokio::test]
+    async fn rotates_nicks_on_renewal() {
+        let cookie_manager = cookie_manager(&[], None);
+
+        assert_eq!(cookie_manager.get_cookies().await.unwrap(), "_ncfa=a; ");
+        cookie_manager.invalidate().await;
+        assert_eq!(cookie_manager.get_cookies().await.unwrap(), "_ncfa=b; ");
+        cookie_manager.invalidate().await;
+        assert_eq!(cookie_manager.get_cookies().await.unwrap(), "_ncfa=a; ");
+    }
+
+    #[tokio::test]
+    async fn does_not_lock_the_cookies_during_the_backoff() {
+        let mut cookie_manager = cookie_manager(&[false, true], None);
+        cookie_manager.login_backoff = std::time::Duration::from_millis(500);
+
+        let (cookies, locked) = tokio::join!(cookie_manager.get_cookies(), async {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            tokio::time::timeout(std::time::Duration::from_millis(100), cookie_manager.state.lock())
+                .await
+                .is_ok()
+        });
+
+        assert_eq!(cookies.unwrap(), "_ncfa=b; ");
+        assert!(locked);
+    }
+
+    #[tokio::test]
+    async fn renews_cookies_when_rejected() {
+        let cookie_manager = cookie_manager(&[], None);
+        let requests = AtomicU32::new(0);
+
+        let response = cookie_manager
+            .get_with("url", |cookies| {
+                let status = if requests.fetch_add(1, Ordering::Relaxed) == 0 { StatusCode::UNAUTHORIZED } else { StatusCode::OK };
+                assert_eq!(cookies, if status == StatusCode::OK { "_ncfa=b; " } else { "_ncfa=a; " });
+                async move { Ok(response(status)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(nicks(&cookie_manager), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn reports_rejected_cookies_as_errors() {
+        let guest_manager = cookie_manager(&[], None);
+        let result = guest_manager.get_with("url", |_| async { Ok(response(StatusCode::FORBIDDEN)) }).await;
+
+        assert_eq!(result.unwrap_err().as_response_error().status_code().as_u16(), StatusCode::BAD_GATEWAY.as_u16());
+        assert_eq!(nicks(&guest_manager).len(), 2);
+
+        let ncfa_manager = cookie_manager(&[], Some("operator"));
+        let result = ncfa_manager.get_with("url", |_| async { Ok(response(StatusCode::UNAUTHORIZED)) }).await;
+
+        assert_eq!(result.unwrap_err().as_response_error().status_code().as_u16(), StatusCode::BAD_GATEWAY.as_u16());
+        assert!(nicks(&ncfa_manager).is_empty());
+    }
 }
@@ -0,0 +1,460 @@
+use country_boundaries::{CountryBoundaries, LatLon};
+use dotenv::dotenv;
+use log::{info, warn};
+use ring::digest;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::sync::OnceLock;
+
+const EARTH_RADIUS: f64 = 6_371_000.0;
+const PROBE_BEARINGS: usize = 16;
+const PROBE_MIN_RADIUS: f64 = 100.0;
+const PROBE_RADIUS_FACTOR: f64 = 2.0;
+/// With the default fallback radius of 50 km all rings fit in this cap, so finding the nearest
+/// border costs at most `PROBE_BEARINGS * (PROBE_MAX_RINGS + PROBE_REFINE_STEPS)` lookups.
+const PROBE_MAX_RINGS: usize = 10;
+const PROBE_REFINE_STEPS: usize = 7;
+const DEFAULT_RULES_PATH: &str = "geocoding_rules.json";
+
+static GEOCODER: OnceLock<BoundaryGeocoder> = OnceLock::new();
+
+/// Loads the boundary data and the geocoding rules, called once at startup.
+pub fn init_geocoder() -> Result<(), String> {
+    let geocoder = BoundaryGeocoder::from_env()?;
+    let _ = GEOCODER.set(geocoder);
+    Ok(())
+}
+
+pub fn geocoder() -> &'static dyn Geocoder {
+    GEOCODER.get().expect("the geocoder is initialized at startup")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeocodeResult {
+    pub country_code: Option<String>,
+    pub subdivision_code: Option<String>,
+    /// Approximate distance in meters to the closest border of `country_code`. For guesses outside
+    /// of every country this is the distance to the country they were assigned to.
+    /// `None` if nothing was found within the fallback radius.
+    pub border_distance: Option<f64>
+}
+
+pub trait Geocoder: Send + Sync {
+    fn geocode(&self, lat: f64, lng: f64) -> GeocodeResult;
+
+    fn subdivision_code(&self, lat: f64, lng: f64) -> Option<String>;
+
+    /// Identifies the boundary data and rules, so stored codes can be traced back to them.
+    fn dataset_version(&self) -> &str;
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GeocodingRules {
+    /// Smaller territories that win over the country they overlap with, e.g. `PR` over `US`.
+    pub priority_countries: HashSet<String>,
+    /// Replaces a code from the boundary data with another one.
+    pub country_overrides: HashMap<String, String>,
+    /// Guesses outside of every country are assigned to the nearest country within this many meters.
+    pub fallback_radius: f64
+}
+
+impl GeocodingRules {
+    fn validate(&self) -> Result<(), String> {
+        if !self.fallback_radius.is_finite() || self.fallback_radius <= 0.0 {
+            return Err(format!("fallbackRadius must be a positive number of meters, got {}", self.fallback_radius));
+        }
+
+        let codes = self.priority_countries
+            .iter()
+            .chain(self.country_overrides.keys())
+            .chain(self.country_overrides.values());
+
+        for code in codes {
+            if code.is_empty() || code.chars().any(|char| char.is_ascii_lowercase() || char.is_whitespace()) {
+                return Err(format!("{:?} is not an upper case country code", code));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for GeocodingRules {
+    fn default() -> Self {
+        GeocodingRules {
+            priority_countries: ["CW", "DO", "PR", "VI", "GU", "MP", "HK", "CX", "ST", "SJ"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            country_overrides: HashMap::new(),
+            fallback_radius: 50_000.0
+        }
+    }
+}
+
+/// Ids of the boundaries containing a point, in the order of the boundary data.
+pub trait Boundaries: Send + Sync {
+    fn ids(&self, lat_lon: LatLon) -> Vec<&str>;
+}
+
+impl Boundaries for CountryBoundaries {
+    fn ids(&self, lat_lon: LatLon) -> Vec<&str> {
+        CountryBoundaries::ids(self, lat_lon)
+    }
+}
+
+/// Geocodes with the `country-boundaries` data in `world.ser` and `states.ser`.
+pub struct BoundaryGeocoder {
+    countries: Box<dyn Boundaries>,
+    states: Box<dyn Boundaries>,
+    rules: GeocodingRules,
+    dataset_version: String
+}
+
+impl BoundaryGeocoder {
+    pub fn new(countries: Box<dyn Boundaries>, states: Box<dyn Boundaries>, rules: GeocodingRules, dataset_version: String) -> Self {
+        BoundaryGeocoder {
+            countries,
+            states,
+            rules,
+            dataset_version
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        dotenv().ok();
+
+        let countries_bytes = fs::read("world.ser").map_err(|err| format!("Could not open world.ser: {}", err))?;
+        let states_bytes = fs::read("states.ser").map_err(|err| format!("Could not open states.ser: {}", err))?;
+
+        let countries = CountryBoundaries::from_reader(countries_bytes.as_slice())
+            .map_err(|err| format!("Could not load world.ser: {}", err))?;
+        let states = CountryBoundaries::from_reader(states_bytes.as_slice())
+            .map_err(|err| format!("Could not load states.ser: {}", err))?;
+
+        let rules_path = env::var("GEOCODING_RULES").unwrap_or_else(|_| String::from(DEFAULT_RULES_PATH));
+        let rules_bytes = fs::read(&rules_path).ok();
+
+        let rules = match &rules_bytes {
+            Some(bytes) => serde_json::from_slice(bytes)
+                .map_err(|err| format!("Could not parse geocoding rules {}: {}", rules_path, err))?,
+            None => {
+                warn!("No geocoding rules found at {}, using the default rules", rules_path);
+                GeocodingRules::default()
+            }
+        };
+
+        rules
+            .validate()
+            .map_err(|err| format!("Invalid geocoding rules {}: {}", rules_path, err))?;
+
+        let dataset_version = env::var("BOUNDARY_DATASET_VERSION")
+            .unwrap_or_else(|_| get_dataset_digest(&[&countries_bytes, &states_bytes], rules_bytes.as_deref()));
+
+        info!("Loaded boundary dataset {}", dataset_version);
+
+        Ok(BoundaryGeocoder::new(Box::new(countries), Box::new(states), rules, dataset_version))
+    }
+
+    fn country_code(&self, lat: f64, lng: f64) -> Option<String> {
+        let mut codes = get_lat_lon(lat, lng).map(|lat_lon| self.countries.ids(lat_lon)).unwrap_or_default();
+        let mut country_code = codes.pop();
+
+        for code in codes {
+            if self.rules.priority_countries.contains(code) {
+                country_code = Some(code);
+                break;
+            }
+        }
+
+        country_code.map(|code| {
+            self.rules
+                .country_overrides
+                .get(code)
+                .cloned()
+                .unwrap_or_else(|| String::from(code))
+        })
+    }
+
+    /// Walks outwards in at most `PROBE_MAX_RINGS` rings until a point with a different country
+    /// code than `country_code` is hit. Every bearing that hit in that ring is refined and the
+    /// closest change is returned as its code together with the approximate distance to it.
+    fn find_nearest_change(&self, lat: f64, lng: f64, country_code: &Option<String>) -> Option<(Option<String>, f64)> {
+        let mut inner_radius = 0.0;
+        let mut radius = PROBE_MIN_RADIUS.min(self.rules.fallback_radius);
+
+        for _ in 0..PROBE_MAX_RINGS {
+            let nearest_change = (0..PROBE_BEARINGS)
+                .filter_map(|bearing_index| {
+                    let bearing = 360.0 * bearing_index as f64 / PROBE_BEARINGS as f64;
+                    let (probe_lat, probe_lng) = get_destination(lat, lng, bearing, radius);
+                    let probe_code = self.country_code(probe_lat, probe_lng);
+
+                    (&probe_code != country_code)
+                        .then(|| self.refine_change(lat, lng, bearing, inner_radius, radius, country_code, probe_code))
+                })
+                .min_by(|a, b| a.1.total_cmp(&b.1));
+
+            if nearest_change.is_some() {
+                return nearest_change;
+            }
+
+            if radius == self.rules.fallback_radius {
+                break;
+            }
+
+            inner_radius = radius;
+            radius = (radius * PROBE_RADIUS_FACTOR).min(self.rules.fallback_radius);
+        }
+
+        None
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn refine_change(
+        &self,
+        lat: f64,
+        lng: f64,
+        bearing: f64,
+        mut inner_radius: f64,
+        mut outer_radius: f64,
+        country_code: &Option<String>,
+        mut outer_code: Option<String>
+    ) -> (Option<String>, f64) {
+        for _ in 0..PROBE_REFINE_STEPS {
+            let radius = (inner_radius + outer_radius) / 2.0;
+            let (probe_lat, probe_lng) = get_destination(lat, lng, bearing, radius);
+            let probe_code = self.country_code(probe_lat, probe_lng);
+
+            if &probe_code == country_code {
+                inner_radius = radius;
+            } else {
+                outer_radius = radius;
+                outer_code = probe_code;
+            }
+        }
+
+        (outer_code, outer_radius)
+    }
+}
+
+impl Geocoder for BoundaryGeocoder {
+    fn geocode(&self, lat: f64, lng: f64) -> GeocodeResult {
+        let country_code = self.country_code(lat, lng);
+        let subdivision_code = self.subdivision_code(lat, lng);
+
+        match country_code {
+            Some(country_code) => {
+                let country_code = Some(country_code);
+                let border_distance = self
+                    .find_nearest_change(lat, lng, &country_code)
+                    .map(|(_, distance)| distance);
+
+                GeocodeResult {
+                    country_code,
+                    subdivision_code,
+                    border_distance
+                }
+            }
+            None => match self.find_nearest_change(lat, lng, &None) {
+                Some((nearest_country_code, distance)) => GeocodeResult {
+                    country_code: nearest_country_code,
+                    subdivision_code,
+                    border_distance: Some(distance)
+                },
+                None => GeocodeResult {
+                    country_code: None,
+                    subdivision_code,
+                    border_distance: None
+                }
+            }
+        }
+    }
+
+    fn subdivision_code(&self, lat: f64, lng: f64) -> Option<String> {
+        let subdivision_codes = get_lat_lon(lat, lng).map(|lat_lon| self.states.ids(lat_lon)).unwrap_or_default();
+        subdivision_codes.into_iter().next().map(String::from)
+    }
+
+    fn dataset_version(&self) -> &str {
+        &self.dataset_version
+    }
+}
+
+/// `None` for coordinates that are not finite, which are outside of every boundary.
+fn get_lat_lon(lat: f64, lng: f64) -> Option<LatLon> {
+    LatLon::new(lat.clamp(-90.0, 90.0), lng).ok()
+}
+
+/// Destination point on a sphere starting at `lat`/`lng` and travelling `distance` meters towards `bearing`.
+fn get_destination(lat: f64, lng: f64, bearing: f64, distance: f64) -> (f64, f64) {
+    let angular_distance = distance / EARTH_RADIUS;
+    let bearing = bearing.to_radians();
+    let lat1 = lat.to_radians();
+    let lng1 = lng.to_radians();
+
+    let lat2 = (lat1.sin() * angular_distance.cos() + lat1.cos() * angular_distance.sin() * bearing.cos()).asin();
+    let lng2 = lng1 + (bearing.sin() * angular_distance.sin() * lat1.cos())
+        .atan2(angular_distance.cos() - lat1.sin() * lat2.sin());
+
+    let lng2 = (lng2.to_degrees() + 540.0) % 360.0 - 180.0;
+
+    (lat2.to_degrees(), lng2)
+}
+
+fn get_dataset_digest(boundaries_bytes: &[&[u8]], rules_bytes: Option<&[u8]>) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+
+    for bytes in boundaries_bytes {
+        context.update(bytes);
+    }
+
+    if let Some(rules_bytes) = rules_bytes {
+        context.update(rules_bytes);
+    }
+
+    context
+        .finish()
+        .as_ref()
+        .iter()
+        .take(8)
+        .fold(String::new(), |mut acc, byte| {
+            write!(&mut acc, "{:02x}", byte).unwrap();
+            acc
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Id, latitude range and longitude range of a rectangular boundary.
+    type StubBoundary = (&'static str, (f64, f64), (f64, f64));
+
+    /// Rectangular boundaries, returned in the order they are listed.
+    struct StubBoundaries(Vec<StubBoundary>);
+
+    impl Boundaries for StubBoundaries {
+        fn ids(&self, lat_lon: LatLon) -> Vec<&str> {
+            self.0
+                .iter()
+                .filter(|(_, (min_lat, max_lat), (min_lng, max_lng))| {
+                    (*min_lat..=*max_lat).contains(&lat_lon.latitude()) && (*min_lng..=*max_lng).contains(&lat_lon.longitude())
+                })
+                .map(|(id, _, _)| *id)
+                .collect()
+        }
+    }
+
+    /// Meters per degree of longitude on the equator.
+    const METERS_PER_DEGREE: f64 = EARTH_RADIUS * std::f64::consts::PI / 180.0;
+
+    fn stub_geocoder(rules: GeocodingRules) -> BoundaryGeocoder {
+        let countries = StubBoundaries(vec![
+            ("AA", (-10.0, 10.0), (0.0, 10.0)),
+            ("PR", (-1.0, 1.0), (24.0, 26.0)),
+            ("US", (-10.0, 10.0), (20.0, 30.0))
+        ]);
+        let states = StubBoundaries(vec![("AA-01", (-10.0, 10.0), (0.0, 5.0))]);
+
+        BoundaryGeocoder::new(Box::new(countries), Box::new(states), rules, String::from("test"))
+    }
+
+    fn assert_near(actual: f64, expected: f64, tolerance: f64) {
+        assert!((actual - expected).abs() <= tolerance, "expected {} to be within {} of {}", actual, tolerance, expected);
+    }
+
+    #[test]
+    fn gets_destination_along_the_equator_and_meridian() {
+        let (lat, lng) = get_destination(0.0, 0.0, 90.0, METERS_PER_DEGREE);
+        assert_near(lat, 0.0, 1e-9);
+        assert_near(lng, 1.0, 1e-9);
+
+        let (lat, lng) = get_destination(0.0, 0.0, 0.0, METERS_PER_DEGREE);
+        assert_near(lat, 1.0, 1e-9);
+        assert_near(lng, 0.0, 1e-9);
+    }
+
+    #[test]
+    fn wraps_destination_across_the_antimeridian() {
+        let (lat, lng) = get_destination(0.0, 179.5, 90.0, METERS_PER_DEGREE);
+        assert_near(lat, 0.0, 1e-9);
+        assert_near(lng, -179.5, 1e-9);
+    }
+
+    #[test]
+    fn prefers_priority_countries() {
+        let geocoder = stub_geocoder(GeocodingRules::default());
+        assert_eq!(geocoder.country_code(0.0, 25.0).as_deref(), Some("PR"));
+
+        let rules = GeocodingRules {
+            priority_countries: HashSet::new(),
+            ..GeocodingRules::default()
+        };
+        let geocoder = stub_geocoder(rules);
+        assert_eq!(geocoder.country_code(0.0, 25.0).as_deref(), Some("US"));
+    }
+
+    #[test]
+    fn applies_country_overrides() {
+        let rules = GeocodingRules {
+            country_overrides: HashMap::from([(String::from("AA"), String::from("BB"))]),
+            ..GeocodingRules::default()
+        };
+        let geocoder = stub_geocoder(rules);
+
+        let result = geocoder.geocode(0.0, 2.0);
+        assert_eq!(result.country_code.as_deref(), Some("BB"));
+        assert_eq!(result.subdivision_code.as_deref(), Some("AA-01"));
+    }
+
+    #[test]
+    fn measures_border_distance_inside_a_country() {
+        let geocoder = stub_geocoder(GeocodingRules::default());
+
+        let result = geocoder.geocode(0.0, 10.0 - 5_000.0 / METERS_PER_DEGREE);
+        assert_eq!(result.country_code.as_deref(), Some("AA"));
+        assert_near(result.border_distance.unwrap(), 5_000.0, 50.0);
+
+        let result = geocoder.geocode(0.0, 5.0);
+        assert_eq!(result.country_code.as_deref(), Some("AA"));
+        assert_eq!(result.border_distance, None);
+    }
+
+    #[test]
+    fn assigns_guesses_within_the_fallback_radius() {
+        let geocoder = stub_geocoder(GeocodingRules::default());
+
+        let result = geocoder.geocode(0.0, -10_000.0 / METERS_PER_DEGREE);
+        assert_eq!(result.country_code.as_deref(), Some("AA"));
+        assert_near(result.border_distance.unwrap(), 10_000.0, 100.0);
+
+        let result = geocoder.geocode(0.0, -60_000.0 / METERS_PER_DEGREE);
+        assert_eq!(result.country_code, None);
+        assert_eq!(result.border_distance, None);
+
+        let rules = GeocodingRules {
+            fallback_radius: 5_000.0,
+            ..GeocodingRules::default()
+        };
+        let result = stub_geocoder(rules).geocode(0.0, -10_000.0 / METERS_PER_DEGREE);
+        assert_eq!(result.country_code, None);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(GeocodingRules::default().validate().is_ok());
+
+        let rules: GeocodingRules = serde_json::from_str(r#"{"fallbackRadius": -1.0}"#).unwrap();
+        assert!(rules.validate().is_err());
+
+        let rules: GeocodingRules = serde_json::from_str(r#"{"countryOverrides": {"aa": "BB"}}"#).unwrap();
+        assert!(rules.validate().is_err());
+
+        assert!(serde_json::from_str::<GeocodingRules>(r#"{"fallbackRadius": "far"}"#).is_err());
+    }
+}
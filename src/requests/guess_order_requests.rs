@@ -0,0 +1,187 @@
+use crate::entities::prelude::Guess;
+use crate::entities::guess;
+use crate::login::get_player_from_session;
+use crate::requests::game_requests::{GameResult, PlayerGame};
+use crate::requests::{average, get_player_team_ids, GameFilter};
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Which team guessed first on a duels round and how long the other team took to follow.
+pub struct GuessOrder<'a> {
+    pub first_team_id: &'a str,
+    /// Seconds between the first guesses of both teams, `None` if only one team guessed.
+    pub gap: Option<f64>
+}
+
+/// Compares the earliest guess of each team on a round. Returns `None` if nobody guessed.
+pub fn get_guess_order<'a>(guesses: &[&'a guess::Model]) -> Option<GuessOrder<'a>> {
+    let mut first_guesses: HashMap<&str, DateTime<Utc>> = HashMap::new();
+
+    for guess in guesses {
+        let date: DateTime<Utc> = guess.date.parse().unwrap();
+        let first_guess = first_guesses.entry(&guess.team_id).or_insert(date);
+        *first_guess = (*first_guess).min(date);
+    }
+
+    let mut first_guesses: Vec<(&str, DateTime<Utc>)> = first_guesses.into_iter().collect();
+    first_guesses.sort_by_key(|(_, date)| *date);
+
+    let (first_team_id, first_date) = first_guesses.first()?;
+
+    Some(GuessOrder {
+        first_team_id,
+        gap: first_guesses.get(1).map(|(_, date)| (*date - *first_date).num_milliseconds() as f64 / 1000.0)
+    })
+}
+
+/// A round of the player's team with the resulting score difference of the best guesses.
+struct RoundOrder {
+    game_id: String,
+    guessed_first: bool,
+    gap: Option<f64>,
+    score_difference: i32
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuessOrderStats {
+    rounds: usize,
+    rounds_won: usize,
+    round_win_rate: Option<f64>,
+    avg_score_difference: Option<f64>,
+    /// Average seconds between the first guesses of both teams.
+    avg_gap: Option<f64>,
+    /// Games where the team guessed first, respectively second, on most rounds.
+    games: usize,
+    wins: usize,
+    game_win_rate: Option<f64>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuessOrderResponse {
+    rounds: usize,
+    guessed_first_rate: Option<f64>,
+    /// The rate GeoGuessr reports in the ranked system progress of the player.
+    reported_guessed_first_rate: Option<f64>,
+    guessed_first: GuessOrderStats,
+    guessed_second: GuessOrderStats
+}
+
+fn get_round_order(game: &PlayerGame, guesses: &[&guess::Model]) -> Option<RoundOrder> {
+    let guess_order = get_guess_order(guesses)?;
+
+    let best_score = |is_team: bool| guesses
+        .iter()
+        .filter(|guess| (guess.team_id == game.team_id) == is_team)
+        .map(|guess| guess.score)
+        .max()
+        .unwrap_or_default();
+
+    Some(RoundOrder {
+        game_id: game.game.id.clone(),
+        guessed_first: guess_order.first_team_id == game.team_id,
+        gap: guess_order.gap,
+        score_difference: best_score(true) - best_score(false)
+    })
+}
+
+fn summarize_guess_order(rounds: &[&RoundOrder], games: &[&PlayerGame]) -> GuessOrderStats {
+    let rounds_won = rounds.iter().filter(|round| round.score_difference > 0).count();
+    let wins = games.iter().filter(|game| game.result() == GameResult::Win).count();
+
+    GuessOrderStats {
+        rounds: rounds.len(),
+        rounds_won,
+        round_win_rate: (!rounds.is_empty()).then(|| rounds_won as f64 / rounds.len() as f64),
+        avg_score_difference: average(rounds.iter().map(|round| round.score_difference as f64)),
+        avg_gap: average(rounds.iter().filter_map(|round| round.gap)),
+        games: games.len(),
+        wins,
+        game_win_rate: (!games.is_empty()).then(|| wins as f64 / games.len() as f64)
+    }
+}
+
+#[get("/stats/guess-order")]
+pub async fn get_guess_order_stats(
+    db: web::Data<DatabaseConnection>,
+    filter: web::Query<GameFilter>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let player = get_player_from_session(&session_id, db).await?;
+    let team_ids = get_player_team_ids(&player.id, db).await.map_err(ErrorInternalServerError)?;
+
+    let games: Vec<PlayerGame> = filter
+        .find_games(&team_ids, db)
+        .await?
+        .into_iter()
+        .map(|game| PlayerGame::new(game, &team_ids))
+        .collect::<Result<_, _>>()?;
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(games.iter().map(|game| game.game.id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut round_guesses: HashMap<&str, Vec<&guess::Model>> = HashMap::new();
+
+    for guess in &guesses {
+        round_guesses.entry(&guess.round_id).or_default().push(guess);
+    }
+
+    let games_by_id: HashMap<&str, &PlayerGame> = games.iter().map(|game| (game.game.id.as_str(), game)).collect();
+
+    let rounds: Vec<RoundOrder> = round_guesses
+        .values()
+        .filter_map(|guesses| {
+            let game = games_by_id.get(guesses[0].game_id.as_str())?;
+            get_round_order(game, guesses)
+        })
+        .collect();
+
+    let mut games_first_rounds: HashMap<&str, (usize, usize)> = HashMap::new();
+
+    for round in &rounds {
+        let (first_rounds, game_rounds) = games_first_rounds.entry(&round.game_id).or_default();
+        *game_rounds += 1;
+
+        if round.guessed_first {
+            *first_rounds += 1;
+        }
+    }
+
+    let (first_games, second_games): (Vec<&PlayerGame>, Vec<&PlayerGame>) = games
+        .iter()
+        .filter(|game| games_first_rounds.contains_key(game.game.id.as_str()))
+        .partition(|game| {
+            let (first_rounds, game_rounds) = games_first_rounds[game.game.id.as_str()];
+            first_rounds * 2 > game_rounds
+        });
+
+    let (first_rounds, second_rounds): (Vec<&RoundOrder>, Vec<&RoundOrder>) = rounds
+        .iter()
+        .partition(|round| round.guessed_first);
+
+    let response = GuessOrderResponse {
+        rounds: rounds.len(),
+        guessed_first_rate: (!rounds.is_empty()).then(|| first_rounds.len() as f64 / rounds.len() as f64),
+        reported_guessed_first_rate: player.guessed_first_rate,
+        guessed_first: summarize_guess_order(&first_rounds, &first_games),
+        guessed_second: summarize_guess_order(&second_rounds, &second_games)
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -0,0 +1,270 @@
+use crate::entities::prelude::{DuelsRound, Guess, Location};
+use crate::entities::{duels_round, guess, location};
+use crate::login::get_player_id_from_session;
+use crate::requests::{get_player_team_ids, GameFilter};
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+const DEFAULT_CELL_SIZE: f64 = 1.0;
+const MIN_CELL_SIZE: f64 = 0.1;
+const MAX_CELL_SIZE: f64 = 10.0;
+
+#[derive(Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum HeatmapFormat {
+    #[default]
+    Grid,
+    GeoJson
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum HeatmapLayer {
+    /// Number of guesses per cell.
+    #[default]
+    Density,
+    /// Average error vector from the round locations in a cell to their guesses.
+    Errors
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HeatmapQuery {
+    #[serde(default)]
+    format: HeatmapFormat,
+    #[serde(default)]
+    layer: HeatmapLayer,
+    /// Cell size in degrees.
+    cell_size: Option<f64>,
+    /// Only rounds in this country.
+    country_code: Option<String>
+}
+
+/// Compact grid: every cell is an array, see the `columns` of the response for its layout.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GridResponse {
+    layer: HeatmapLayer,
+    cell_size: f64,
+    columns: &'static [&'static str],
+    cells: Vec<Vec<f64>>
+}
+
+#[derive(Default)]
+struct Cell {
+    count: usize,
+    score_sum: i64,
+    distance_sum: f64,
+    lat_sum: f64,
+    lng_sum: f64,
+    error_lat_sum: f64,
+    error_lng_sum: f64
+}
+
+type CellKey = (i32, i32);
+
+fn get_cell_key(lat: f64, lng: f64, cell_size: f64) -> CellKey {
+    (((lat + 90.0) / cell_size).floor() as i32, ((lng + 180.0) / cell_size).floor() as i32)
+}
+
+/// South west corner of a cell.
+fn get_cell_corner(key: CellKey, cell_size: f64) -> (f64, f64) {
+    (key.0 as f64 * cell_size - 90.0, key.1 as f64 * cell_size - 180.0)
+}
+
+/// Longitude difference in `-180..180`, so errors across the antimeridian stay short.
+fn get_lng_delta(from: f64, to: f64) -> f64 {
+    (to - from + 540.0) % 360.0 - 180.0
+}
+
+fn get_density_grid(cells: &HashMap<CellKey, Cell>, cell_size: f64) -> GridResponse {
+    let cells = cells
+        .iter()
+        .map(|(key, cell)| {
+            let (south, west) = get_cell_corner(*key, cell_size);
+            vec![south, west, cell.count as f64, cell.score_sum as f64 / cell.count as f64]
+        })
+        .collect();
+
+    GridResponse {
+        layer: HeatmapLayer::Density,
+        cell_size,
+        columns: &["south", "west", "count", "avgScore"],
+        cells
+    }
+}
+
+fn get_error_vector(cell: &Cell) -> (f64, f64, f64, f64) {
+    let count = cell.count as f64;
+    let lat = cell.lat_sum / count;
+    let lng = cell.lng_sum / count;
+    let guess_lat = lat + cell.error_lat_sum / count;
+    let guess_lng = (lng + cell.error_lng_sum / count + 540.0) % 360.0 - 180.0;
+
+    (lat, lng, guess_lat, guess_lng)
+}
+
+fn get_error_grid(cells: &HashMap<CellKey, Cell>, cell_size: f64) -> GridResponse {
+    let cells = cells
+        .values()
+        .map(|cell| {
+            let (lat, lng, guess_lat, guess_lng) = get_error_vector(cell);
+            vec![lat, lng, guess_lat, guess_lng, cell.count as f64, cell.distance_sum / cell.count as f64]
+        })
+        .collect();
+
+    GridResponse {
+        layer: HeatmapLayer::Errors,
+        cell_size,
+        columns: &["lat", "lng", "guessLat", "guessLng", "count", "avgDistance"],
+        cells
+    }
+}
+
+fn get_density_geo_json(cells: &HashMap<CellKey, Cell>, cell_size: f64) -> Value {
+    let features: Vec<Value> = cells
+        .iter()
+        .map(|(key, cell)| {
+            let (south, west) = get_cell_corner(*key, cell_size);
+            let (north, east) = (south + cell_size, west + cell_size);
+
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Polygon",
+                    "coordinates": [[[west, south], [east, south], [east, north], [west, north], [west, south]]]
+                },
+                "properties": {
+                    "count": cell.count,
+                    "avgScore": cell.score_sum as f64 / cell.count as f64
+                }
+            })
+        })
+        .collect();
+
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+fn get_error_geo_json(cells: &HashMap<CellKey, Cell>) -> Value {
+    let features: Vec<Value> = cells
+        .values()
+        .map(|cell| {
+            let (lat, lng, guess_lat, guess_lng) = get_error_vector(cell);
+
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": [[lng, lat], [guess_lng, guess_lat]]
+                },
+                "properties": {
+                    "count": cell.count,
+                    "avgScore": cell.score_sum as f64 / cell.count as f64,
+                    "avgDistance": cell.distance_sum / cell.count as f64
+                }
+            })
+        })
+        .collect();
+
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+#[get("/stats/heatmap")]
+pub async fn get_heatmap(
+    db: web::Data<DatabaseConnection>,
+    filter: web::Query<GameFilter>,
+    query: web::Query<HeatmapQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let query = query.into_inner();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+
+    let cell_size = query.cell_size.unwrap_or(DEFAULT_CELL_SIZE);
+
+    if !cell_size.is_finite() {
+        return Err(ErrorBadRequest("Invalid cell size!"));
+    }
+
+    let cell_size = cell_size.clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+
+    let team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+    let games = filter.find_games(&team_ids, db).await?;
+
+    let mut guess_query = Guess::find()
+        .filter(guess::Column::GameId.is_in(games.into_iter().map(|game| game.id)))
+        .filter(guess::Column::TeamId.is_in(&team_ids))
+        .filter(guess::Column::IsTeamsBest.eq(true));
+
+    if let Some(country_code) = &query.country_code {
+        guess_query = guess_query.filter(guess::Column::RoundCountryCode.eq(country_code.to_ascii_uppercase()));
+    }
+
+    let guesses = guess_query.all(db).await.map_err(ErrorInternalServerError)?;
+    let mut cells: HashMap<CellKey, Cell> = HashMap::new();
+
+    match query.layer {
+        HeatmapLayer::Density => {
+            for guess in guesses {
+                let cell = cells.entry(get_cell_key(guess.lat, guess.lng, cell_size)).or_default();
+                cell.count += 1;
+                cell.score_sum += guess.score as i64;
+                cell.distance_sum += guess.distance;
+            }
+        }
+        HeatmapLayer::Errors => {
+            let round_locations: HashMap<String, String> = DuelsRound::find()
+                .filter(duels_round::Column::Id.is_in(guesses.iter().map(|guess| guess.round_id.clone())))
+                .all(db)
+                .await
+                .map_err(ErrorInternalServerError)?
+                .into_iter()
+                .map(|round| (round.id, round.location_id))
+                .collect();
+
+            let locations: HashMap<String, location::Model> = Location::find()
+                .filter(location::Column::Id.is_in(round_locations.values().cloned()))
+                .all(db)
+                .await
+                .map_err(ErrorInternalServerError)?
+                .into_iter()
+                .map(|location| (location.id.clone(), location))
+                .collect();
+
+            for guess in guesses {
+                let Some(location) = round_locations.get(&guess.round_id).and_then(|location_id| locations.get(location_id)) else {
+                    continue;
+                };
+
+                let cell = cells.entry(get_cell_key(location.lat, location.lng, cell_size)).or_default();
+                cell.count += 1;
+                cell.score_sum += guess.score as i64;
+                cell.distance_sum += guess.distance;
+                cell.lat_sum += location.lat;
+                cell.lng_sum += location.lng;
+                cell.error_lat_sum += guess.lat - location.lat;
+                cell.error_lng_sum += get_lng_delta(location.lng, guess.lng);
+            }
+        }
+    }
+
+    let response = match (query.format, query.layer) {
+        (HeatmapFormat::Grid, HeatmapLayer::Density) => json!(get_density_grid(&cells, cell_size)),
+        (HeatmapFormat::Grid, HeatmapLayer::Errors) => json!(get_error_grid(&cells, cell_size)),
+        (HeatmapFormat::GeoJson, HeatmapLayer::Density) => get_density_geo_json(&cells, cell_size),
+        (HeatmapFormat::GeoJson, HeatmapLayer::Errors) => get_error_geo_json(&cells)
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
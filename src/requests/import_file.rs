@@ -0,0 +1,232 @@
+use crate::entities::prelude::{DuelsGame, SoloGame};
+use crate::entities::{duels_game, solo_game};
+use crate::requests::import_games::merge_games_data;
+use crate::requests::insertion_requests::{build_game_data, build_solo_game_data, insert_games_into_db, insert_solo_game_into_db};
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError, ErrorPayloadTooLarge};
+use actix_web::{post, web, Error, HttpResponse, Responder};
+use futures::StreamExt;
+use log::error;
+use reqwest::Client;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::{Cursor, Read};
+
+const MAX_FILE_SIZE: usize = 64 * 1024 * 1024;
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_UNZIPPED_SIZE: u64 = 256 * 1024 * 1024;
+const MAX_GAMES_PER_FILE: usize = 1000;
+const ZIP_MAGIC_NUMBER: &[u8] = b"PK\x03\x04";
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ImportFileResponse {
+    duels_games: usize,
+    solo_games: usize,
+    skipped: usize,
+    failed: usize
+}
+
+enum ImportedGame {
+    Duels(Box<crate::geo_guessr::DuelsGame>),
+    Solo(Box<crate::geo_guessr::SoloGame>)
+}
+
+impl ImportedGame {
+    fn from_value(value: Value) -> Option<ImportedGame> {
+        if value.get("gameId").is_some() {
+            serde_json::from_value(value).ok().map(|game| ImportedGame::Duels(Box::new(game)))
+        } else if value.get("token").is_some() {
+            serde_json::from_value(value).ok().map(|game| ImportedGame::Solo(Box::new(game)))
+        } else {
+            None
+        }
+    }
+}
+
+fn push_document(document: Option<Value>, documents: &mut Vec<Option<Value>>) -> Result<(), Error> {
+    if documents.len() >= MAX_GAMES_PER_FILE {
+        return Err(ErrorBadRequest(format!("A file can contain at most {} games!", MAX_GAMES_PER_FILE)));
+    }
+
+    documents.push(document);
+    Ok(())
+}
+
+/// Splits a text file into JSON documents. The file is either a single game,
+/// a JSON array of games or newline delimited JSON (one game per line).
+fn parse_json_documents(text: &str, documents: &mut Vec<Option<Value>>) -> Result<(), Error> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(Value::Array(values)) => {
+            if documents.len() + values.len() > MAX_GAMES_PER_FILE {
+                return Err(ErrorBadRequest(format!("A file can contain at most {} games!", MAX_GAMES_PER_FILE)));
+            }
+
+            documents.extend(values.into_iter().map(Some));
+            Ok(())
+        }
+        Ok(value) => push_document(Some(value), documents),
+        Err(_) => {
+            for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                push_document(serde_json::from_str::<Value>(line).ok(), documents)?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+fn parse_zip_documents(bytes: &[u8]) -> Result<Vec<Option<Value>>, Error> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))
+        .map_err(|err| ErrorBadRequest(format!("Could not read zip file! Error: {}", err)))?;
+    let mut documents = Vec::new();
+    let mut total_size = 0;
+
+    for index in 0..archive.len() {
+        let file = archive
+            .by_index(index)
+            .map_err(|err| ErrorBadRequest(format!("Could not read zip file! Error: {}", err)))?;
+
+        if file.is_dir() {
+            continue;
+        }
+
+        // The declared size can be forged, so the read itself is limited as well.
+        let entry_limit = MAX_ENTRY_SIZE.min(MAX_UNZIPPED_SIZE - total_size);
+
+        if file.size() > entry_limit {
+            return Err(ErrorPayloadTooLarge("Unzipped file is too large!"));
+        }
+
+        let mut text = String::new();
+
+        if file.take(entry_limit + 1).read_to_string(&mut text).is_err() {
+            push_document(None, &mut documents)?;
+            continue;
+        }
+
+        if text.len() as u64 > entry_limit {
+            return Err(ErrorPayloadTooLarge("Unzipped file is too large!"));
+        }
+
+        total_size += text.len() as u64;
+        parse_json_documents(&text, &mut documents)?;
+    }
+
+    Ok(documents)
+}
+
+fn parse_file(bytes: &[u8]) -> Result<Vec<Option<Value>>, Error> {
+    if bytes.starts_with(ZIP_MAGIC_NUMBER) {
+        return parse_zip_documents(bytes);
+    }
+
+    let text = std::str::from_utf8(bytes).map_err(|_| ErrorBadRequest("File is neither a zip nor a JSON file!"))?;
+    let mut documents = Vec::new();
+    parse_json_documents(text, &mut documents)?;
+    Ok(documents)
+}
+
+#[post("/import/file")]
+async fn import_game_file(
+    mut payload: web::Payload,
+    db: web::Data<DatabaseConnection>,
+) -> Result<impl Responder, Error> {
+    let mut bytes = web::BytesMut::new();
+
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+
+        if bytes.len() + chunk.len() > MAX_FILE_SIZE {
+            return Err(ErrorPayloadTooLarge("File is too large!"));
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if bytes.is_empty() {
+        return Err(ErrorBadRequest("File is empty!"));
+    }
+
+    let documents = parse_file(&bytes)?;
+
+    let mut response = ImportFileResponse::default();
+    let mut duels_games = Vec::new();
+    let mut solo_games = Vec::new();
+
+    for document in documents {
+        match document.and_then(ImportedGame::from_value) {
+            Some(ImportedGame::Duels(game)) => duels_games.push(*game),
+            Some(ImportedGame::Solo(game)) => solo_games.push(*game),
+            None => response.failed += 1
+        }
+    }
+
+    let db = db.get_ref();
+    let client = Client::new();
+
+    let existing_duels_ids: HashSet<String> = DuelsGame::find()
+        .filter(duels_game::Column::Id.is_in(duels_games.iter().map(|game| game.game_id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| game.id)
+        .collect();
+
+    let existing_solo_ids: HashSet<String> = SoloGame::find()
+        .filter(solo_game::Column::Id.is_in(solo_games.iter().map(|game| game.token.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| game.id)
+        .collect();
+
+    let mut seen_ids = HashSet::new();
+    let mut game_data_list = Vec::new();
+
+    for game in duels_games {
+        if existing_duels_ids.contains(&game.game_id) || !seen_ids.insert(game.game_id.clone()) {
+            response.skipped += 1;
+            continue;
+        }
+
+        match build_game_data(game, &client, db).await {
+            Ok(game_data) => game_data_list.push(game_data),
+            Err(err) => {
+                error!("Could not import duels game from file! Error: {}", err);
+                response.failed += 1;
+            }
+        }
+    }
+
+    response.duels_games = game_data_list.len();
+
+    if !game_data_list.is_empty() {
+        insert_games_into_db(merge_games_data(game_data_list), db).await?;
+    }
+
+    for game in solo_games {
+        if existing_solo_ids.contains(&game.token) || !seen_ids.insert(game.token.clone()) {
+            response.skipped += 1;
+            continue;
+        }
+
+        let result = match build_solo_game_data(game, &client, db).await {
+            Ok(solo_game_data) => insert_solo_game_into_db(solo_game_data, db).await,
+            Err(err) => Err(err)
+        };
+
+        match result {
+            Ok(()) => response.solo_games += 1,
+            Err(err) => {
+                error!("Could not import solo game from file! Error: {}", err);
+                response.failed += 1;
+            }
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -1,5 +1,5 @@
-use crate::geo_guessr::{Entry, Payload};
-use crate::requests::{geo_login, GamesData};
+use crate::geo_guessr::{BattleRoyaleMode, Entry, Payload};
+use crate::requests::{GameData, GamesData};
 use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
 use actix_web::{post, web, Error, HttpResponse, Responder};
 use futures::future::join_all;
@@ -7,9 +7,10 @@ use reqwest::Client;
 use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use serde::Deserialize;
 use std::collections::HashSet;
-use crate::entities::duels_game;
-use crate::entities::prelude::DuelsGame;
-use crate::requests::insertion_requests::{get_game_data, insert_games_into_db};
+use std::str::FromStr;
+use crate::entities::{br_game, duels_game};
+use crate::entities::prelude::{BrGame, DuelsGame};
+use crate::requests::insertion_requests::{get_battle_royale_game_data, get_game_data, insert_battle_royale_games_into_db, insert_games_into_db};
 
 const REQUEST_CHUNK_SIZE: usize = 50;
 
@@ -32,47 +33,23 @@ fn remove_duplicates<T: ActiveModelTrait>(active_models: Vec<T>) -> Vec<T> {
     unique_active_models
 }
 
-async fn insert_games(
-    game_ids: Vec<String>,
-    db: &DatabaseConnection,
-    client: &Client,
-    cookies: String,
-) -> Result<usize, Error> {
-    let existing_games= match DuelsGame::find()
-        .filter(duels_game::Column::Id.is_in(game_ids.clone()))
-        .all(db)
-        .await {
-        Ok(games) => games,
-        Err(err) => return Err(ErrorInternalServerError(err))
-    };
-    
-    let existing_ids: HashSet<String> = existing_games.into_iter().map(|game| game.id).collect();
-    let valid_game_ids: Vec<String> = game_ids.into_iter().filter(|id| !existing_ids.contains(id)).collect();
-    
+pub fn merge_games_data(game_data_list: Vec<GameData>) -> GamesData {
     let mut duels_games = Vec::new();
     let mut rounds = Vec::new();
     let mut guesses = Vec::new();
     let mut locations = Vec::new();
+    let mut round_results = Vec::new();
     let mut players = Vec::new();
     let mut comp_teams = Vec::new();
     let mut fun_teams = Vec::new();
     let mut maps = Vec::new();
 
-    let mut futures = Vec::with_capacity(REQUEST_CHUNK_SIZE);
-    futures.append(
-        &mut valid_game_ids
-            .iter()
-            .map(|game_id| get_game_data(game_id.as_str(), client, cookies.clone(), db))
-            .collect()
-    );
-
-    let results = join_all(futures).await;
-
-    for mut game_data in results.into_iter().flatten() {
+    for mut game_data in game_data_list {
         duels_games.push(game_data.duels_game);
         rounds.append(&mut game_data.rounds);
         guesses.append(&mut game_data.guesses);
         locations.append(&mut game_data.locations);
+        round_results.append(&mut game_data.round_results);
         players.append(&mut game_data.players);
         comp_teams.append(&mut game_data.comp_teams);
         fun_teams.append(&mut game_data.fun_teams);
@@ -86,22 +63,78 @@ async fn insert_games(
     fun_teams = remove_duplicates(fun_teams);
     maps = remove_duplicates(maps);
 
-    let games_data = GamesData {
+    GamesData {
         duels_games,
         rounds,
         guesses,
         locations,
+        round_results,
         players,
         comp_teams,
         fun_teams,
         maps,
+    }
+}
+
+async fn insert_games(
+    game_ids: Vec<String>,
+    db: &DatabaseConnection,
+    client: &Client,
+) -> Result<usize, Error> {
+    let existing_games= match DuelsGame::find()
+        .filter(duels_game::Column::Id.is_in(game_ids.clone()))
+        .all(db)
+        .await {
+        Ok(games) => games,
+        Err(err) => return Err(ErrorInternalServerError(err))
     };
+    
+    let existing_ids: HashSet<String> = existing_games.into_iter().map(|game| game.id).collect();
+    let valid_game_ids: Vec<String> = game_ids.into_iter().filter(|id| !existing_ids.contains(id)).collect();
+    
+    let mut futures = Vec::with_capacity(REQUEST_CHUNK_SIZE);
+    futures.append(
+        &mut valid_game_ids
+            .iter()
+            .map(|game_id| get_game_data(game_id.as_str(), client, db))
+            .collect()
+    );
+
+    let results = join_all(futures).await;
+    let games_data = merge_games_data(results.into_iter().flatten().collect());
 
     insert_games_into_db(games_data, db).await?;
 
     Ok(valid_game_ids.len())
 }
 
+async fn insert_battle_royale_games(
+    game_ids: Vec<String>,
+    db: &DatabaseConnection,
+    client: &Client,
+) -> Result<usize, Error> {
+    let existing_ids: HashSet<String> = BrGame::find()
+        .filter(br_game::Column::Id.is_in(game_ids.clone()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| game.id)
+        .collect();
+
+    let valid_game_ids: Vec<String> = game_ids.into_iter().filter(|id| !existing_ids.contains(id)).collect();
+
+    let results = join_all(
+        valid_game_ids
+            .iter()
+            .map(|game_id| get_battle_royale_game_data(game_id.as_str(), client))
+    ).await;
+
+    insert_battle_royale_games_into_db(results.into_iter().flatten().collect(), db).await?;
+
+    Ok(valid_game_ids.len())
+}
+
 #[post("/import-games")]
 async fn import_recent_games(
     request: web::Json<ImportRecentGamesRequest>,
@@ -113,27 +146,29 @@ async fn import_recent_games(
 
     let db = db.get_ref();
     let client = Client::new();
-    let cookies = geo_login::get_cookies().await;
     let mut game_ids = Vec::new();
+    let mut battle_royale_game_ids = Vec::new();
 
     for entry in request.entries.iter() {
         if let Ok(payloads) = serde_json::from_str::<Vec<Payload>>(&entry.payload) {
-            game_ids.extend(
-                payloads
-                    .into_iter()
-                    .filter(|payload| payload.payload.game_mode.as_str() != "LiveChallenge")
-                    .map(|payload| payload.payload.game_id)
-            );
-            
-            if game_ids.len() >= REQUEST_CHUNK_SIZE {
-                break;
+            for payload in payloads {
+                if game_ids.len() + battle_royale_game_ids.len() >= REQUEST_CHUNK_SIZE {
+                    break;
+                }
+
+                let game_mode = payload.payload.game_mode.as_str();
+
+                if BattleRoyaleMode::from_str(game_mode).is_ok() {
+                    battle_royale_game_ids.push(payload.payload.game_id);
+                } else if game_mode != "LiveChallenge" {
+                    game_ids.push(payload.payload.game_id);
+                }
             }
         }
     }
-    
-    game_ids.truncate(REQUEST_CHUNK_SIZE);
 
-    let inserted_games = insert_games(game_ids, db, &client, cookies).await?;
+    let inserted_games = insert_games(game_ids, db, &client).await?
+        + insert_battle_royale_games(battle_royale_game_ids, db, &client).await?;
 
     Ok(HttpResponse::Ok().json(inserted_games))
 }
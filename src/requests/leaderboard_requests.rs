@@ -0,0 +1,380 @@
+use crate::entities::leaderboard_entry::ActiveModel as LeaderboardEntryModel;
+use crate::entities::prelude::{DuelsGame, Guess, LeaderboardEntry, SoloGame, User};
+use crate::entities::{duels_game, guess, leaderboard_entry, player, solo_game, user};
+use crate::login::get_user_from_session;
+use crate::requests::game_requests::get_players;
+use crate::requests::count_if;
+use crate::requests::scoring::MAX_SCORE;
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use dotenv::dotenv;
+use log::{error, info};
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{ActiveValue, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter, QueryOrder, QuerySelect, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use uuid::Uuid;
+
+const INSERT_BATCH_SIZE: usize = 1000;
+const LEADERBOARD_SIZE: usize = 100;
+const MIN_ROUNDS: i64 = 20;
+const DEFAULT_REFRESH_MINUTES: u64 = 60;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum LeaderboardKind {
+    /// Highest average score per country.
+    BestAverageScore,
+    /// Most rounds with a perfect score.
+    MostPerfectRounds,
+    /// Lowest average guess time.
+    FastestGuessTime,
+    /// Highest share of guesses in the right country, per country.
+    HighestHitRate
+}
+
+impl LeaderboardKind {
+    const ALL: [LeaderboardKind; 4] = [
+        LeaderboardKind::BestAverageScore,
+        LeaderboardKind::MostPerfectRounds,
+        LeaderboardKind::FastestGuessTime,
+        LeaderboardKind::HighestHitRate
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            LeaderboardKind::BestAverageScore => "BestAverageScore",
+            LeaderboardKind::MostPerfectRounds => "MostPerfectRounds",
+            LeaderboardKind::FastestGuessTime => "FastestGuessTime",
+            LeaderboardKind::HighestHitRate => "HighestHitRate"
+        }
+    }
+
+    fn is_per_region(&self) -> bool {
+        matches!(self, LeaderboardKind::BestAverageScore | LeaderboardKind::HighestHitRate)
+    }
+
+    fn is_ascending(&self) -> bool {
+        matches!(self, LeaderboardKind::FastestGuessTime)
+    }
+
+    /// Returns `None` if the player does not qualify for the leaderboard.
+    fn get_value(&self, stats: &PlayerStats) -> Option<f64> {
+        match self {
+            LeaderboardKind::BestAverageScore => {
+                (stats.rounds >= MIN_ROUNDS).then(|| stats.score_sum as f64 / stats.rounds as f64)
+            }
+            LeaderboardKind::MostPerfectRounds => {
+                (stats.perfect_rounds > 0).then_some(stats.perfect_rounds as f64)
+            }
+            LeaderboardKind::FastestGuessTime => {
+                (stats.timed_rounds >= MIN_ROUNDS).then(|| stats.time_sum as f64 / stats.timed_rounds as f64)
+            }
+            LeaderboardKind::HighestHitRate => {
+                (stats.rounds >= MIN_ROUNDS).then(|| stats.hits as f64 / stats.rounds as f64)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct PlayerStats {
+    rounds: i64,
+    score_sum: i64,
+    perfect_rounds: i64,
+    timed_rounds: i64,
+    time_sum: i64,
+    hits: i64
+}
+
+impl PlayerStats {
+    fn add(&mut self, other: &PlayerStats) {
+        self.rounds += other.rounds;
+        self.score_sum += other.score_sum;
+        self.perfect_rounds += other.perfect_rounds;
+        self.timed_rounds += other.timed_rounds;
+        self.time_sum += other.time_sum;
+        self.hits += other.hits;
+    }
+}
+
+/// Geo mode, map and region a leaderboard is restricted to. `None` stands for all of them.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct LeaderboardScope {
+    geo_mode: Option<String>,
+    map_id: Option<String>,
+    region: Option<String>
+}
+
+fn get_scopes(geo_mode: &str, map_id: &str, region: &str) -> Vec<LeaderboardScope> {
+    let mut scopes = Vec::with_capacity(8);
+
+    for geo_mode in [None, Some(geo_mode)] {
+        for map_id in [None, Some(map_id)] {
+            for region in [None, Some(region)] {
+                scopes.push(LeaderboardScope {
+                    geo_mode: geo_mode.map(String::from),
+                    map_id: map_id.map(String::from),
+                    region: region.map(String::from)
+                });
+            }
+        }
+    }
+
+    scopes
+}
+
+type PlayerStatsRow = (String, String, String, String, i64, i64, i64, i64, Option<i64>, i64);
+
+/// Sums up the guesses of players with a public profile per geo mode, map and region of the
+/// games in `E`, which is either the duels or the solo game table, that match `games`.
+async fn get_player_stats<E: EntityTrait>(
+    game_id: E::Column,
+    geo_mode: E::Column,
+    map_id: E::Column,
+    games: Condition,
+    db: &DatabaseConnection
+) -> Result<Vec<(String, LeaderboardScope, PlayerStats)>, DbErr> {
+    let rows: Vec<PlayerStatsRow> = Guess::find()
+        .select_only()
+        .column(guess::Column::PlayerId)
+        .column(geo_mode)
+        .column(map_id)
+        .column(guess::Column::RoundCountryCode)
+        .column_as(guess::Column::Id.count(), "rounds")
+        .column_as(guess::Column::Score.sum(), "score_sum")
+        .column_as(count_if(guess::Column::Score.eq(MAX_SCORE)), "perfect_rounds")
+        .column_as(guess::Column::Time.count(), "timed_rounds")
+        .column_as(guess::Column::Time.sum(), "time_sum")
+        .column_as(count_if(Expr::col((Guess, guess::Column::CountryCode)).equals((Guess, guess::Column::RoundCountryCode))), "hits")
+        .join(JoinType::InnerJoin, Guess::belongs_to(E::default()).from(guess::Column::GameId).to(game_id).into())
+        // Several users can link the same player, joining them would count the guesses more than once.
+        .filter(Expr::exists(
+            Query::select()
+                .expr(Expr::val(1))
+                .from(User)
+                .and_where(Expr::col((User, user::Column::PlayerId)).equals((Guess, guess::Column::PlayerId)))
+                .and_where(Expr::col((User, user::Column::IsProfilePublic)).eq(true))
+                .to_owned()
+        ))
+        .filter(games)
+        .group_by(guess::Column::PlayerId)
+        .group_by(geo_mode)
+        .group_by(map_id)
+        .group_by(guess::Column::RoundCountryCode)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(player_id, geo_mode, map_id, region, rounds, score_sum, perfect_rounds, timed_rounds, time_sum, hits)| (
+            player_id,
+            LeaderboardScope {
+                geo_mode: Some(geo_mode),
+                map_id: Some(map_id),
+                region: Some(region)
+            },
+            PlayerStats {
+                rounds,
+                score_sum,
+                perfect_rounds,
+                timed_rounds,
+                time_sum: time_sum.unwrap_or_default(),
+                hits
+            }
+        ))
+        .collect())
+}
+
+/// Recomputes every leaderboard from the guesses of players with a public profile and replaces
+/// the stored ones. The guesses are summed up per player, geo mode, map and region by the
+/// database, then rolled up into all the broader scopes.
+pub async fn refresh_leaderboards(db: &DatabaseConnection) -> Result<usize, DbErr> {
+    let mut rows = get_player_stats::<DuelsGame>(duels_game::Column::Id, duels_game::Column::GeoMode, duels_game::Column::MapId, Condition::all(), db).await?;
+    // Streak rounds are not scored.
+    let solo_games = Condition::all().add(solo_game::Column::StreakType.is_null());
+    rows.append(&mut get_player_stats::<SoloGame>(solo_game::Column::Id, solo_game::Column::GeoMode, solo_game::Column::MapId, solo_games, db).await?);
+
+    let mut stats: HashMap<LeaderboardScope, HashMap<String, PlayerStats>> = HashMap::new();
+
+    for (player_id, row_scope, row_stats) in rows {
+        let (Some(geo_mode), Some(map_id), Some(region)) = (&row_scope.geo_mode, &row_scope.map_id, &row_scope.region) else {
+            continue;
+        };
+
+        for scope in get_scopes(geo_mode, map_id, region) {
+            stats
+                .entry(scope)
+                .or_default()
+                .entry(player_id.clone())
+                .or_default()
+                .add(&row_stats);
+        }
+    }
+
+    let computed_at = Utc::now().to_string();
+    let mut entries = Vec::new();
+
+    for (scope, players_stats) in &stats {
+        for kind in LeaderboardKind::ALL {
+            if kind.is_per_region() != scope.region.is_some() {
+                continue;
+            }
+
+            let mut ranking: Vec<(&String, f64, i64)> = players_stats
+                .iter()
+                .filter_map(|(player_id, player_stats)| {
+                    kind.get_value(player_stats).map(|value| (player_id, value, player_stats.rounds))
+                })
+                .collect();
+
+            ranking.sort_by(|a, b| {
+                let ordering = a.1.total_cmp(&b.1);
+                if kind.is_ascending() { ordering } else { ordering.reverse() }
+            });
+
+            for (rank, (player_id, value, rounds)) in ranking.into_iter().take(LEADERBOARD_SIZE).enumerate() {
+                entries.push(LeaderboardEntryModel {
+                    id: ActiveValue::Set(Uuid::new_v4().to_string()),
+                    kind: ActiveValue::Set(String::from(kind.as_str())),
+                    geo_mode: ActiveValue::Set(scope.geo_mode.clone()),
+                    map_id: ActiveValue::Set(scope.map_id.clone()),
+                    region: ActiveValue::Set(scope.region.clone()),
+                    rank: ActiveValue::Set(rank as i32 + 1),
+                    player_id: ActiveValue::Set(player_id.clone()),
+                    value: ActiveValue::Set(value),
+                    rounds: ActiveValue::Set(rounds as i32),
+                    computed_at: ActiveValue::Set(computed_at.clone())
+                });
+            }
+        }
+    }
+
+    let entry_count = entries.len();
+
+    db.transaction::<_, _, DbErr>(|txn| {
+        Box::pin(async move {
+            LeaderboardEntry::delete_many().exec(txn).await?;
+
+            let mut entries = entries.into_iter().peekable();
+
+            while entries.peek().is_some() {
+                let batch: Vec<LeaderboardEntryModel> = entries.by_ref().take(INSERT_BATCH_SIZE).collect();
+                LeaderboardEntry::insert_many(batch).exec(txn).await?;
+            }
+
+            Ok(())
+        })
+    })
+        .await
+        .map_err(|err| DbErr::Custom(err.to_string()))?;
+
+    Ok(entry_count)
+}
+
+/// Refreshes the leaderboards every `LEADERBOARD_REFRESH_MINUTES` minutes in the background.
+pub fn spawn_leaderboard_refresh(db: DatabaseConnection) {
+    dotenv().ok();
+
+    let refresh_minutes = env::var("LEADERBOARD_REFRESH_MINUTES")
+        .ok()
+        .and_then(|minutes| minutes.parse().ok())
+        .unwrap_or(DEFAULT_REFRESH_MINUTES)
+        // A zero period would make the interval panic.
+        .max(1);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(refresh_minutes * 60));
+
+        loop {
+            interval.tick().await;
+
+            match refresh_leaderboards(&db).await {
+                Ok(entry_count) => info!("Refreshed leaderboards with {} entries", entry_count),
+                Err(err) => error!("Could not refresh leaderboards! Error: {}", err)
+            }
+        }
+    });
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderboardQuery {
+    geo_mode: Option<String>,
+    map_id: Option<String>,
+    /// Country code, required for per-country leaderboards.
+    region: Option<String>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderboardRow {
+    rank: i32,
+    player: Option<player::Model>,
+    value: f64,
+    rounds: i32
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LeaderboardResponse {
+    computed_at: Option<String>,
+    entries: Vec<LeaderboardRow>
+}
+
+#[get("/leaderboards/{kind}")]
+pub async fn get_leaderboard(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<LeaderboardKind>,
+    query: web::Query<LeaderboardQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let kind = path.into_inner();
+    let query = query.into_inner();
+    get_user_from_session(&session_id, db).await?;
+
+    let filter_column = |column: leaderboard_entry::Column, value: Option<String>| match value {
+        Some(value) => column.eq(value),
+        None => column.is_null()
+    };
+
+    let entries = LeaderboardEntry::find()
+        .filter(leaderboard_entry::Column::Kind.eq(kind.as_str()))
+        .filter(filter_column(leaderboard_entry::Column::GeoMode, query.geo_mode))
+        .filter(filter_column(leaderboard_entry::Column::MapId, query.map_id))
+        .filter(filter_column(leaderboard_entry::Column::Region, query.region.map(|region| region.to_ascii_uppercase())))
+        .order_by_asc(leaderboard_entry::Column::Rank)
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let player_ids: Vec<String> = entries.iter().map(|entry| entry.player_id.clone()).collect();
+    let mut players = get_players(&player_ids, db).await.map_err(ErrorInternalServerError)?;
+
+    let response = LeaderboardResponse {
+        computed_at: entries.first().map(|entry| entry.computed_at.clone()),
+        entries: entries
+            .into_iter()
+            .map(|entry| LeaderboardRow {
+                rank: entry.rank,
+                player: players.remove(&entry.player_id),
+                value: entry.value,
+                rounds: entry.rounds
+            })
+            .collect()
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
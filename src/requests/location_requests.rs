@@ -0,0 +1,341 @@
+use crate::entities::prelude::{BrGuess, BrRound, DuelsRound, Guess, Location, SoloGame, SoloRound};
+use crate::entities::{br_guess, br_round, duels_round, guess, location, solo_game, solo_round};
+use crate::login::get_user_from_session;
+use crate::requests::average;
+use crate::requests::scoring::MAX_SCORE;
+use actix_web::error::{ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::sea_query::{Alias, Expr, Func, Order, Query, SelectStatement, UnionType};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter, QuerySelect, QueryTrait, SelectGetableTuple, Selector};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const SCORE_BUCKET_SIZE: i32 = 500;
+const COMMON_WRONG_COUNTRIES: usize = 10;
+const DEFAULT_MIN_GUESSES: i64 = 10;
+const DEFAULT_HARDEST_LIMIT: usize = 50;
+const MAX_HARDEST_LIMIT: usize = 200;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LocationInfo {
+    pano_id: String,
+    lat: f64,
+    lng: f64,
+    heading: f64,
+    pitch: f64,
+    zoom: f64,
+    country_code: String,
+    subdivision_code: Option<String>
+}
+
+impl From<location::Model> for LocationInfo {
+    fn from(location: location::Model) -> Self {
+        LocationInfo {
+            pano_id: location.id,
+            lat: location.lat,
+            lng: location.lng,
+            heading: location.heading,
+            pitch: location.pitch,
+            zoom: location.zoom,
+            country_code: location.country_code,
+            subdivision_code: location.subdivision_code
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScoreBucket {
+    /// Inclusive lower bound of the bucket, a perfect score has its own bucket.
+    min_score: i32,
+    guesses: usize
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WrongCountry {
+    country_code: Option<String>,
+    guesses: usize
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LocationStatsResponse {
+    location: LocationInfo,
+    /// Duels, solo and battle royale rounds.
+    rounds: usize,
+    guesses: usize,
+    /// Battle royale guesses have no score, the score stats only cover duels and solo guesses.
+    avg_score: Option<f64>,
+    avg_distance: Option<f64>,
+    hit_rate: Option<f64>,
+    score_distribution: Vec<ScoreBucket>,
+    common_wrong_countries: Vec<WrongCountry>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HardestLocationsQuery {
+    country_code: Option<String>,
+    min_guesses: Option<i64>,
+    limit: Option<usize>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HardLocation {
+    location: LocationInfo,
+    guesses: i64,
+    avg_score: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HardestLocationsResponse {
+    locations: Vec<HardLocation>
+}
+
+fn get_score_distribution(guesses: &[guess::Model]) -> Vec<ScoreBucket> {
+    let mut buckets: Vec<ScoreBucket> = (0..MAX_SCORE)
+        .step_by(SCORE_BUCKET_SIZE as usize)
+        .chain([MAX_SCORE])
+        .map(|min_score| ScoreBucket { min_score, guesses: 0 })
+        .collect();
+
+    for guess in guesses {
+        let index = if guess.score >= MAX_SCORE {
+            buckets.len() - 1
+        } else {
+            (guess.score.max(0) / SCORE_BUCKET_SIZE) as usize
+        };
+
+        buckets[index].guesses += 1;
+    }
+
+    buckets
+}
+
+fn get_common_wrong_countries<'a>(guess_country_codes: impl Iterator<Item = Option<&'a str>>, country_code: &str) -> Vec<WrongCountry> {
+    let mut wrong_countries: HashMap<Option<&str>, usize> = HashMap::new();
+
+    for guess_country_code in guess_country_codes {
+        if guess_country_code != Some(country_code) {
+            *wrong_countries.entry(guess_country_code).or_default() += 1;
+        }
+    }
+
+    let mut wrong_countries: Vec<WrongCountry> = wrong_countries
+        .into_iter()
+        .map(|(country_code, guesses)| WrongCountry {
+            country_code: country_code.map(String::from),
+            guesses
+        })
+        .collect();
+
+    wrong_countries.sort_by_key(|wrong_country| Reverse(wrong_country.guesses));
+    wrong_countries.truncate(COMMON_WRONG_COUNTRIES);
+    wrong_countries
+}
+
+#[get("/locations/{pano_id}")]
+pub async fn get_location_stats(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let pano_id = path.into_inner();
+    get_user_from_session(&session_id, db).await?;
+
+    let location = match Location::find_by_id(&pano_id).one(db).await {
+        Ok(Some(location)) => location,
+        Ok(None) => return Err(ErrorNotFound("Location not found!")),
+        Err(err) => return Err(ErrorInternalServerError(err))
+    };
+
+    let mut round_ids: Vec<String> = DuelsRound::find()
+        .filter(duels_round::Column::LocationId.eq(&pano_id))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|round| round.id)
+        .collect();
+
+    round_ids.extend(
+        SoloRound::find()
+            .join(JoinType::InnerJoin, solo_round::Entity::belongs_to(solo_game::Entity).from(solo_round::Column::GameId).to(solo_game::Column::Id).into())
+            .filter(solo_round::Column::LocationId.eq(&pano_id))
+            .filter(solo_game::Column::StreakType.is_null())
+            .all(db)
+            .await
+            .map_err(ErrorInternalServerError)?
+            .into_iter()
+            .map(|round| round.id)
+    );
+
+    let br_round_ids: Vec<String> = BrRound::find()
+        .filter(br_round::Column::LocationId.eq(&pano_id))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|round| round.id)
+        .collect();
+
+    let guesses = Guess::find()
+        .filter(guess::Column::RoundId.is_in(&round_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let br_guesses = BrGuess::find()
+        .filter(br_guess::Column::RoundId.is_in(&br_round_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let guess_country_codes = || {
+        guesses
+            .iter()
+            .map(|guess| guess.country_code.as_deref())
+            .chain(br_guesses.iter().map(|guess| guess.country_code.as_deref()))
+    };
+
+    let guess_count = guesses.len() + br_guesses.len();
+    let hits = guess_country_codes()
+        .filter(|guess_country_code| *guess_country_code == Some(location.country_code.as_str()))
+        .count();
+
+    let response = LocationStatsResponse {
+        rounds: round_ids.len() + br_round_ids.len(),
+        guesses: guess_count,
+        avg_score: average(guesses.iter().map(|guess| guess.score as f64)),
+        avg_distance: average(guesses.iter().map(|guess| guess.distance).chain(br_guesses.iter().map(|guess| guess.distance))),
+        hit_rate: (guess_count > 0).then(|| hits as f64 / guess_count as f64),
+        score_distribution: get_score_distribution(&guesses),
+        common_wrong_countries: get_common_wrong_countries(guess_country_codes(), &location.country_code),
+        location: location.into()
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Location id and score of every guess in the rounds of `R`, which is either the duels or the
+/// solo round table, optionally restricted to locations in one country.
+fn get_location_guesses<R: EntityTrait>(
+    round_id: R::Column,
+    location_id: R::Column,
+    country_code: Option<&str>
+) -> SelectStatement {
+    let mut query = Guess::find()
+        .select_only()
+        .column_as(location_id, "location_id")
+        .column_as(guess::Column::Score, "score")
+        .join(JoinType::InnerJoin, guess::Entity::belongs_to(R::default()).from(guess::Column::RoundId).to(round_id).into());
+
+    if let Some(country_code) = country_code {
+        query = query
+            .join(JoinType::InnerJoin, R::belongs_to(Location).from(location_id).to(location::Column::Id).into())
+            .filter(location::Column::CountryCode.eq(country_code));
+    }
+
+    query.into_query()
+}
+
+/// Locations with at least `min_guesses` guesses and the lowest average score, as
+/// `(location_id, score_sum, guesses)`. Battle royale guesses have no score and are not counted.
+async fn get_hardest_location_sums(
+    country_code: Option<&str>,
+    min_guesses: i64,
+    limit: usize,
+    db: &DatabaseConnection
+) -> Result<Vec<(String, i64, i64)>, DbErr> {
+    let location_id = Alias::new("location_id");
+    let score = Alias::new("score");
+
+    let mut location_guesses = get_location_guesses::<DuelsRound>(duels_round::Column::Id, duels_round::Column::LocationId, country_code);
+    let mut solo_location_guesses = get_location_guesses::<SoloRound>(solo_round::Column::Id, solo_round::Column::LocationId, country_code);
+    solo_location_guesses
+        .inner_join(SoloGame, Expr::col((SoloGame, solo_game::Column::Id)).equals((SoloRound, solo_round::Column::GameId)))
+        .and_where(Expr::col((SoloGame, solo_game::Column::StreakType)).is_null());
+    location_guesses.union(UnionType::All, solo_location_guesses);
+
+    let query = Query::select()
+        .column(location_id.clone())
+        .expr_as(Expr::col(score.clone()).sum().cast_as(Alias::new("bigint")), Alias::new("score_sum"))
+        .expr_as(Expr::col(score.clone()).count(), Alias::new("guesses"))
+        .from_subquery(location_guesses, Alias::new("location_guess"))
+        .group_by_col(location_id.clone())
+        .and_having(Expr::expr(Expr::col(score.clone()).count()).gte(min_guesses))
+        .order_by_expr(Func::avg(Expr::col(score.clone())).into(), Order::Asc)
+        .order_by_expr(Expr::col(score).count(), Order::Desc)
+        .order_by(location_id, Order::Asc)
+        .limit(limit as u64)
+        .to_owned();
+
+    Selector::<SelectGetableTuple<(String, i64, i64)>>::into_tuple(query)
+        .all(db)
+        .await
+}
+
+#[get("/locations/hardest")]
+pub async fn get_hardest_locations(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<HardestLocationsQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let query = query.into_inner();
+    get_user_from_session(&session_id, db).await?;
+
+    let min_guesses = query.min_guesses.unwrap_or(DEFAULT_MIN_GUESSES).max(1);
+    let limit = query.limit.unwrap_or(DEFAULT_HARDEST_LIMIT).clamp(1, MAX_HARDEST_LIMIT);
+
+    let country_code = query.country_code.map(|country_code| country_code.to_ascii_uppercase());
+
+    let averages: Vec<(String, i64, f64)> = get_hardest_location_sums(country_code.as_deref(), min_guesses, limit, db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|(location_id, score_sum, guesses)| (location_id, guesses, score_sum as f64 / guesses as f64))
+        .collect();
+
+    let mut locations: HashMap<String, location::Model> = Location::find()
+        .filter(location::Column::Id.is_in(averages.iter().map(|(location_id, _, _)| location_id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|location| (location.id.clone(), location))
+        .collect();
+
+    let hard_locations = averages
+        .into_iter()
+        .filter_map(|(location_id, guesses, avg_score)| {
+            locations.remove(&location_id).map(|location| HardLocation {
+                location: location.into(),
+                guesses,
+                avg_score
+            })
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(HardestLocationsResponse { locations: hard_locations }))
+}
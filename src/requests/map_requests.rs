@@ -0,0 +1,400 @@
+use crate::entities::prelude::{BrRound, DuelsGame, DuelsRound, Guess, Map, SoloGame, SoloRound};
+use crate::entities::{br_game, br_round, duels_game, duels_round, guess, location, map, solo_game, solo_round};
+use crate::login::{get_player_id_from_session, get_user_from_session};
+use crate::requests::game_requests::{GameResult, PlayerGame};
+use crate::requests::{average, get_player_team_ids, GameFilter};
+use actix_web::error::{ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::sea_query::Expr;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter, QuerySelect, RelationTrait};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+/// z-score of the 95% confidence intervals.
+const CONFIDENCE_Z: f64 = 1.96;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Frequency {
+    rounds: i64,
+    /// Share of all rounds on the map.
+    frequency: f64,
+    /// Bounds of the 95% Wilson score interval of `frequency`.
+    lower_bound: f64,
+    upper_bound: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SubdivisionFrequency {
+    subdivision_code: String,
+    #[serde(flatten)]
+    frequency: Frequency
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountryFrequency {
+    country_code: String,
+    #[serde(flatten)]
+    frequency: Frequency,
+    subdivisions: Vec<SubdivisionFrequency>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MapDistributionResponse {
+    map: map::Model,
+    rounds: i64,
+    countries: Vec<CountryFrequency>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MapSummary {
+    #[serde(flatten)]
+    map: map::Model,
+    duels_games: i64,
+    solo_games: i64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MapsResponse {
+    maps: Vec<MapSummary>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlayerMapStats {
+    map_id: String,
+    name: Option<String>,
+    games: usize,
+    duels_games: usize,
+    solo_games: usize,
+    /// Duels results, solo games have no winner.
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    win_rate: Option<f64>,
+    /// Average round score over the duels and solo games on this map.
+    avg_score: Option<f64>,
+    duels_avg_score: Option<f64>,
+    solo_avg_score: Option<f64>,
+    /// Average rating change over the rated games on this map.
+    avg_rating_change: Option<f64>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlayerMapsResponse {
+    maps: Vec<PlayerMapStats>
+}
+
+impl Frequency {
+    fn new(rounds: i64, total_rounds: i64) -> Self {
+        let n = total_rounds as f64;
+        let p = rounds as f64 / n;
+        let z2 = CONFIDENCE_Z * CONFIDENCE_Z;
+
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let margin = CONFIDENCE_Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+
+        Frequency {
+            rounds,
+            frequency: p,
+            lower_bound: (center - margin).max(0.0),
+            upper_bound: (center + margin).min(1.0)
+        }
+    }
+}
+
+/// Counts the duels, solo and battle royale rounds on a map per country and subdivision of their location.
+async fn get_round_counts(map_id: &str, db: &DatabaseConnection) -> Result<Vec<(String, Option<String>, i64)>, DbErr> {
+    let duels_counts: Vec<(String, Option<String>, i64)> = DuelsRound::find()
+        .select_only()
+        .column(location::Column::CountryCode)
+        .column(location::Column::SubdivisionCode)
+        .column_as(Expr::col((duels_round::Entity, duels_round::Column::Id)).count(), "rounds")
+        .join(JoinType::InnerJoin, duels_round::Relation::Game.def())
+        .join(
+            JoinType::InnerJoin,
+            duels_round::Entity::belongs_to(location::Entity)
+                .from(duels_round::Column::LocationId)
+                .to(location::Column::Id)
+                .into()
+        )
+        .filter(duels_game::Column::MapId.eq(map_id))
+        .group_by(location::Column::CountryCode)
+        .group_by(location::Column::SubdivisionCode)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let solo_counts: Vec<(String, Option<String>, i64)> = SoloRound::find()
+        .select_only()
+        .column(location::Column::CountryCode)
+        .column(location::Column::SubdivisionCode)
+        .column_as(Expr::col((solo_round::Entity, solo_round::Column::Id)).count(), "rounds")
+        .join(
+            JoinType::InnerJoin,
+            solo_round::Entity::belongs_to(solo_game::Entity)
+                .from(solo_round::Column::GameId)
+                .to(solo_game::Column::Id)
+                .into()
+        )
+        .join(
+            JoinType::InnerJoin,
+            solo_round::Entity::belongs_to(location::Entity)
+                .from(solo_round::Column::LocationId)
+                .to(location::Column::Id)
+                .into()
+        )
+        .filter(solo_game::Column::MapId.eq(map_id))
+        .group_by(location::Column::CountryCode)
+        .group_by(location::Column::SubdivisionCode)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let br_counts: Vec<(String, Option<String>, i64)> = BrRound::find()
+        .select_only()
+        .column(location::Column::CountryCode)
+        .column(location::Column::SubdivisionCode)
+        .column_as(Expr::col((br_round::Entity, br_round::Column::Id)).count(), "rounds")
+        .join(JoinType::InnerJoin, br_round::Relation::Game.def())
+        .join(
+            JoinType::InnerJoin,
+            br_round::Entity::belongs_to(location::Entity)
+                .from(br_round::Column::LocationId)
+                .to(location::Column::Id)
+                .into()
+        )
+        .filter(br_game::Column::MapId.eq(map_id))
+        .group_by(location::Column::CountryCode)
+        .group_by(location::Column::SubdivisionCode)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(duels_counts.into_iter().chain(solo_counts).chain(br_counts).collect())
+}
+
+#[get("/maps/{map_id}/distribution")]
+pub async fn get_map_distribution(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let map_id = path.into_inner();
+    get_user_from_session(&session_id, db).await?;
+
+    let map = match Map::find_by_id(&map_id).one(db).await {
+        Ok(Some(map)) => map,
+        Ok(None) => return Err(ErrorNotFound("Map not found!")),
+        Err(err) => return Err(ErrorInternalServerError(err))
+    };
+
+    let round_counts = get_round_counts(&map_id, db).await.map_err(ErrorInternalServerError)?;
+    let total_rounds: i64 = round_counts.iter().map(|(_, _, rounds)| rounds).sum();
+
+    let mut countries: BTreeMap<String, (i64, BTreeMap<String, i64>)> = BTreeMap::new();
+
+    for (country_code, subdivision_code, rounds) in round_counts {
+        let (country_rounds, subdivisions) = countries.entry(country_code).or_default();
+        *country_rounds += rounds;
+
+        if let Some(subdivision_code) = subdivision_code {
+            *subdivisions.entry(subdivision_code).or_default() += rounds;
+        }
+    }
+
+    let mut country_frequencies: Vec<CountryFrequency> = countries
+        .into_iter()
+        .map(|(country_code, (rounds, subdivisions))| {
+            let mut subdivisions: Vec<SubdivisionFrequency> = subdivisions
+                .into_iter()
+                .map(|(subdivision_code, rounds)| SubdivisionFrequency {
+                    subdivision_code,
+                    frequency: Frequency::new(rounds, total_rounds)
+                })
+                .collect();
+
+            subdivisions.sort_by_key(|subdivision| Reverse(subdivision.frequency.rounds));
+
+            CountryFrequency {
+                country_code,
+                frequency: Frequency::new(rounds, total_rounds),
+                subdivisions
+            }
+        })
+        .collect();
+
+    country_frequencies.sort_by_key(|country| Reverse(country.frequency.rounds));
+
+    let response = MapDistributionResponse {
+        map,
+        rounds: total_rounds,
+        countries: country_frequencies
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Counts the games per map id of a game table.
+async fn get_game_counts<E: EntityTrait>(map_id_column: E::Column, db: &DatabaseConnection) -> Result<HashMap<String, i64>, DbErr> {
+    let counts: Vec<(String, i64)> = E::find()
+        .select_only()
+        .column(map_id_column)
+        .column_as(Expr::col(map_id_column).count(), "games")
+        .group_by(map_id_column)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(counts.into_iter().collect())
+}
+
+#[get("/maps")]
+pub async fn get_maps(
+    db: web::Data<DatabaseConnection>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    get_user_from_session(&session_id, db).await?;
+
+    let maps = Map::find().all(db).await.map_err(ErrorInternalServerError)?;
+    let duels_counts = get_game_counts::<DuelsGame>(duels_game::Column::MapId, db).await.map_err(ErrorInternalServerError)?;
+    let solo_counts = get_game_counts::<SoloGame>(solo_game::Column::MapId, db).await.map_err(ErrorInternalServerError)?;
+
+    let mut maps: Vec<MapSummary> = maps
+        .into_iter()
+        .map(|map| MapSummary {
+            duels_games: duels_counts.get(&map.id).copied().unwrap_or_default(),
+            solo_games: solo_counts.get(&map.id).copied().unwrap_or_default(),
+            map
+        })
+        .collect();
+
+    maps.sort_by_key(|map| Reverse(map.duels_games + map.solo_games));
+
+    Ok(HttpResponse::Ok().json(MapsResponse { maps }))
+}
+
+#[get("/stats/maps")]
+pub async fn get_maps_stats(
+    db: web::Data<DatabaseConnection>,
+    filter: web::Query<GameFilter>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+    let team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+
+    let games: Vec<PlayerGame> = filter
+        .find_games(&team_ids, db)
+        .await?
+        .into_iter()
+        .map(|game| PlayerGame::new(game, &team_ids))
+        .collect::<Result<_, _>>()?;
+    let solo_games = filter.find_solo_games(&player_id, db).await?;
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(games.iter().map(|game| game.game.id.clone())))
+        .filter(guess::Column::TeamId.is_in(&team_ids))
+        .filter(guess::Column::IsTeamsBest.eq(true))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let solo_guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(solo_games.iter().map(|game| game.id.clone())))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut game_scores: HashMap<&str, Vec<i32>> = HashMap::new();
+
+    for guess in guesses.iter().chain(solo_guesses.iter()) {
+        game_scores.entry(&guess.game_id).or_default().push(guess.score);
+    }
+
+    let mut maps_games: HashMap<&str, (Vec<&PlayerGame>, Vec<&solo_game::Model>)> = HashMap::new();
+
+    for game in &games {
+        maps_games.entry(&game.game.map_id).or_default().0.push(game);
+    }
+    for game in &solo_games {
+        maps_games.entry(&game.map_id).or_default().1.push(game);
+    }
+
+    let map_names: HashMap<String, String> = Map::find()
+        .filter(map::Column::Id.is_in(maps_games.keys().copied()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|map| (map.id, map.name))
+        .collect();
+
+    let get_scores = |game_ids: Vec<&str>| -> Vec<f64> {
+        game_ids
+            .into_iter()
+            .filter_map(|game_id| game_scores.get(game_id))
+            .flatten()
+            .map(|score| *score as f64)
+            .collect()
+    };
+
+    let mut maps: Vec<PlayerMapStats> = maps_games
+        .into_iter()
+        .map(|(map_id, (games, solo_games))| {
+            let wins = games.iter().filter(|game| game.result() == GameResult::Win).count();
+            let duels_scores = get_scores(games.iter().map(|game| game.game.id.as_str()).collect());
+            let solo_scores = get_scores(solo_games.iter().map(|game| game.id.as_str()).collect());
+
+            PlayerMapStats {
+                map_id: String::from(map_id),
+                name: map_names.get(map_id).cloned(),
+                games: games.len() + solo_games.len(),
+                duels_games: games.len(),
+                solo_games: solo_games.len(),
+                wins,
+                losses: games.iter().filter(|game| game.result() == GameResult::Loss).count(),
+                draws: games.iter().filter(|game| game.result() == GameResult::Draw).count(),
+                win_rate: (!games.is_empty()).then(|| wins as f64 / games.len() as f64),
+                avg_score: average(duels_scores.iter().chain(solo_scores.iter()).copied()),
+                duels_avg_score: average(duels_scores.into_iter()),
+                solo_avg_score: average(solo_scores.into_iter()),
+                avg_rating_change: average(games.iter().filter_map(|game| game.rating_change()).map(|change| change as f64))
+            }
+        })
+        .collect();
+
+    maps.sort_by_key(|map| Reverse(map.games));
+
+    Ok(HttpResponse::Ok().json(PlayerMapsResponse { maps }))
+}
@@ -1,3 +1,7 @@
+use crate::entities::br_game::ActiveModel as BrGameModel;
+use crate::entities::br_guess::ActiveModel as BrGuessModel;
+use crate::entities::br_player::ActiveModel as BrPlayerModel;
+use crate::entities::br_round::ActiveModel as BrRoundModel;
 use crate::entities::comp_team::ActiveModel as CompTeamModel;
 use crate::entities::duels_game::ActiveModel as DuelsGameModel;
 use crate::entities::duels_round::ActiveModel as DuelsRoundModel;
@@ -6,45 +10,49 @@ use crate::entities::guess::ActiveModel as GuessModel;
 use crate::entities::location::ActiveModel as LocationModel;
 use crate::entities::map::ActiveModel as MapModel;
 use crate::entities::player::ActiveModel as PlayerModel;
+use crate::entities::round_result::ActiveModel as RoundResultModel;
+use crate::entities::solo_game::ActiveModel as SoloGameModel;
+use crate::entities::solo_round::ActiveModel as SoloRoundModel;
+use crate::entities::prelude::{CompTeam, DuelsGame, FunTeam, SoloGame};
+use crate::entities::{comp_team, duels_game, fun_team, solo_game};
+use crate::requests::game_requests::start_time_expr;
+use actix_web::error::{ErrorBadRequest, ErrorInternalServerError};
+use actix_web::Error;
 use chrono::{DateTime, TimeDelta, Utc};
-use country_boundaries::CountryBoundaries;
 use lazy_static::lazy_static;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use sea_query::extension::postgres::PgFunc;
+use sea_query::{Expr, Func, SimpleExpr};
 use std::collections::{HashMap, HashSet};
-use std::fs::File;
 use tokio::sync::Mutex;
 
 pub mod insertion_requests;
 pub mod general_stats_requests;
 pub mod geo_login;
 pub mod import_games;
+pub mod import_file;
+pub mod transform;
+pub mod geocoder;
+pub mod scoring;
 pub mod country_stats_request;
+pub mod game_requests;
+pub mod opponent_stats_requests;
+pub mod team_stats_requests;
+pub mod leaderboard_requests;
+pub mod location_requests;
+pub mod heatmap_requests;
+pub mod export_requests;
+pub mod timing_requests;
+pub mod guess_order_requests;
+pub mod distance_stats_requests;
+pub mod recommendation_requests;
+pub mod map_requests;
+pub mod streak_requests;
 
 const CASH_EXPIRE_TIME: TimeDelta = TimeDelta::seconds(90);
 
 lazy_static! {
-    static ref COUNTRY_BOUNDARIES: CountryBoundaries = CountryBoundaries::from_reader(
-        File::open("world.ser")
-        .expect("failed to open world.ser")
-    ).expect("failed to load country boundaries");
-    
-    static ref STATE_BOUNDARIES: CountryBoundaries = CountryBoundaries::from_reader(
-        File::open("states.ser")
-        .expect("failed to open states.ser")
-    ).expect("failed to load country boundaries");
-    
-    static ref PRIORITY_COUNTRIES: HashSet<String> = [
-        String::from("CW"),
-        String::from("DO"),
-        String::from("PR"), 
-        String::from("VI"), 
-        String::from("GU"), 
-        String::from("MP"),
-        String::from("HK"),
-        String::from("CX"), 
-        String::from("ST"), 
-        String::from("SJ")
-    ].into_iter().collect();
-    
     static ref CASHED_ITEMS: Mutex<HashMap<String, DateTime<Utc>>> = Mutex::new(HashMap::new());
 }
 
@@ -53,6 +61,7 @@ pub struct GamesData {
     pub rounds: Vec<DuelsRoundModel>,
     pub guesses: Vec<GuessModel>,
     pub locations: Vec<LocationModel>,
+    pub round_results: Vec<RoundResultModel>,
     pub players: Vec<PlayerModel>,
     pub comp_teams: Vec<CompTeamModel>,
     pub fun_teams: Vec<FunTeamModel>,
@@ -64,8 +73,138 @@ pub struct GameData {
     pub rounds: Vec<DuelsRoundModel>,
     pub guesses: Vec<GuessModel>,
     pub locations: Vec<LocationModel>,
+    pub round_results: Vec<RoundResultModel>,
     pub players: Vec<PlayerModel>,
     pub comp_teams: Vec<CompTeamModel>,
     pub fun_teams: Vec<FunTeamModel>,
     pub map: MapModel
-}
\ No newline at end of file
+}
+
+pub struct SoloGameData {
+    pub solo_game: SoloGameModel,
+    pub rounds: Vec<SoloRoundModel>,
+    pub guesses: Vec<GuessModel>,
+    pub locations: Vec<LocationModel>,
+    pub player: Option<PlayerModel>,
+    pub map: MapModel
+}
+
+pub struct BattleRoyaleGameData {
+    pub br_game: BrGameModel,
+    pub players: Vec<BrPlayerModel>,
+    pub rounds: Vec<BrRoundModel>,
+    pub guesses: Vec<BrGuessModel>,
+    pub locations: Vec<LocationModel>
+}
+
+/// Mean of the values, `None` if there are none.
+pub fn average<I: Iterator<Item = f64>>(values: I) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Number of rows matching `condition`, as an aggregate expression.
+pub fn count_if(condition: SimpleExpr) -> SimpleExpr {
+    Func::sum(Expr::case(condition, 1).finally(0)).into()
+}
+
+/// Ids of every team the player played in: the player id itself for 1v1 duels,
+/// their competitive teams and their fun teams.
+pub async fn get_player_team_ids(player_id: &str, db: &DatabaseConnection) -> Result<HashSet<String>, DbErr> {
+    let mut team_ids: HashSet<String> = [String::from(player_id)].into_iter().collect();
+
+    let comp_teams = CompTeam::find()
+        .filter(comp_team::Column::PlayerId1.eq(player_id).or(comp_team::Column::PlayerId2.eq(player_id)))
+        .all(db)
+        .await?;
+
+    let fun_teams = FunTeam::find()
+        .filter(Expr::val(player_id).eq(PgFunc::any(Expr::col(fun_team::Column::PlayerIds))))
+        .all(db)
+        .await?;
+
+    team_ids.extend(comp_teams.into_iter().map(|team| team.team_id));
+    team_ids.extend(fun_teams.into_iter().map(|team| team.team_id));
+
+    Ok(team_ids)
+}
+
+/// Parses an optional RFC 3339 query parameter.
+pub fn parse_date(date: &Option<String>) -> Result<Option<DateTime<Utc>>, Error> {
+    match date {
+        Some(date) => DateTime::parse_from_rfc3339(date)
+            .map(|date| Some(date.with_timezone(&Utc)))
+            .map_err(|_| ErrorBadRequest(format!("Invalid date `{}`!", date))),
+        None => Ok(None)
+    }
+}
+
+/// Query filters shared by the stats endpoints. Dates are RFC 3339 and inclusive.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GameFilter {
+    pub team_game_mode: Option<String>,
+    pub geo_mode: Option<String>,
+    pub map_id: Option<String>,
+    pub from: Option<String>,
+    pub to: Option<String>
+}
+
+impl GameFilter {
+    /// Finds the duels games of the given teams that match the filter.
+    pub async fn find_games(&self, team_ids: &HashSet<String>, db: &DatabaseConnection) -> Result<Vec<duels_game::Model>, Error> {
+        let from = parse_date(&self.from)?;
+        let to = parse_date(&self.to)?;
+
+        let mut query = DuelsGame::find()
+            .filter(duels_game::Column::TeamId1.is_in(team_ids).or(duels_game::Column::TeamId2.is_in(team_ids)));
+
+        if let Some(team_game_mode) = &self.team_game_mode {
+            query = query.filter(duels_game::Column::TeamGameMode.eq(team_game_mode));
+        }
+        if let Some(geo_mode) = &self.geo_mode {
+            query = query.filter(duels_game::Column::GeoMode.eq(geo_mode));
+        }
+        if let Some(map_id) = &self.map_id {
+            query = query.filter(duels_game::Column::MapId.eq(map_id));
+        }
+        if let Some(from) = from {
+            query = query.filter(Expr::expr(start_time_expr(Expr::col(duels_game::Column::StartTime).into())).gte(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(Expr::expr(start_time_expr(Expr::col(duels_game::Column::StartTime).into())).lte(to));
+        }
+
+        query.all(db).await.map_err(ErrorInternalServerError)
+    }
+
+    /// Finds the scored solo games of the player that match the filter. Solo games have no
+    /// team game mode, so there are none if the filter asks for one.
+    pub async fn find_solo_games(&self, player_id: &str, db: &DatabaseConnection) -> Result<Vec<solo_game::Model>, Error> {
+        if self.team_game_mode.is_some() {
+            return Ok(Vec::new());
+        }
+
+        let from = parse_date(&self.from)?;
+        let to = parse_date(&self.to)?;
+
+        let mut query = SoloGame::find()
+            .filter(solo_game::Column::PlayerId.eq(player_id))
+            .filter(solo_game::Column::StreakType.is_null());
+
+        if let Some(geo_mode) = &self.geo_mode {
+            query = query.filter(solo_game::Column::GeoMode.eq(geo_mode));
+        }
+        if let Some(map_id) = &self.map_id {
+            query = query.filter(solo_game::Column::MapId.eq(map_id));
+        }
+        if let Some(from) = from {
+            query = query.filter(Expr::expr(start_time_expr(Expr::col(solo_game::Column::StartTime).into())).gte(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(Expr::expr(start_time_expr(Expr::col(solo_game::Column::StartTime).into())).lte(to));
+        }
+
+        query.all(db).await.map_err(ErrorInternalServerError)
+    }
+}
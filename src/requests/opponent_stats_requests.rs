@@ -0,0 +1,383 @@
+use crate::entities::prelude::{DuelsGame, Guess};
+use crate::entities::{duels_game, guess};
+use crate::geo_guessr::TeamGameMode;
+use crate::login::get_player_id_from_session;
+use crate::requests::game_requests::{get_players, get_teams_members, opponent_team_expr, player_team_expr, start_time_expr, GameResult, PlayerGame};
+use crate::requests::{average, count_if, get_player_team_ids};
+use actix_web::error::{ErrorInternalServerError, ErrorNotFound, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::{Alias, Expr, Func, SimpleExpr};
+use sea_orm::{ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, JoinType, Order, QueryFilter, QueryOrder, QuerySelect, RelationTrait};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+const DEFAULT_OPPONENTS_LIMIT: usize = 50;
+const MAX_OPPONENTS_LIMIT: usize = 200;
+
+/// Scores of the best guess of both teams on a single round. A team that did not guess scored 0.
+struct RoundScore {
+    country_code: String,
+    score: i32,
+    opponent_score: i32,
+    distance: Option<f64>,
+    opponent_distance: Option<f64>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpponentSummary {
+    opponent_team_id: String,
+    team_game_mode: String,
+    opponent_names: Vec<String>,
+    games: usize,
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    avg_score_difference: Option<f64>,
+    rating_change: i32,
+    last_played: String
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpponentsResponse {
+    opponents: Vec<OpponentSummary>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HeadToHeadGame {
+    id: String,
+    team_game_mode: String,
+    start_time: String,
+    result: GameResult,
+    rating_change: Option<i32>,
+    score_difference: i32
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountryComparison {
+    country_code: String,
+    rounds: usize,
+    rounds_won: usize,
+    avg_score: f64,
+    opponent_avg_score: f64,
+    avg_distance: Option<f64>,
+    opponent_avg_distance: Option<f64>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HeadToHeadResponse {
+    opponents: Vec<OpponentSummary>,
+    games: Vec<HeadToHeadGame>,
+    countries: Vec<CountryComparison>
+}
+
+async fn get_session_team_ids(http_request: &HttpRequest, db: &DatabaseConnection) -> Result<HashSet<String>, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+    get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)
+}
+
+fn is_player_game(team_ids: &HashSet<String>) -> Condition {
+    Condition::any()
+        .add(duels_game::Column::TeamId1.is_in(team_ids.iter().cloned()))
+        .add(duels_game::Column::TeamId2.is_in(team_ids.iter().cloned()))
+}
+
+fn opponent_expr(team_ids: &HashSet<String>) -> SimpleExpr {
+    opponent_team_expr(team_ids, duels_game::Column::TeamId1, duels_game::Column::TeamId2)
+}
+
+/// Groups by the selected `opponent_team_id` column, repeating `opponent_expr` in the
+/// `GROUP BY` would bind the team ids again and Postgres would not match it to the select.
+fn opponent_team_id_column() -> SimpleExpr {
+    Expr::col(Alias::new("opponent_team_id")).into()
+}
+
+async fn get_round_scores(games: &[&PlayerGame], db: &DatabaseConnection) -> Result<HashMap<String, Vec<RoundScore>>, DbErr> {
+    let team_ids: HashMap<&str, &str> = games
+        .iter()
+        .map(|game| (game.game.id.as_str(), game.team_id.as_str()))
+        .collect();
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(team_ids.keys().copied()))
+        .filter(guess::Column::IsTeamsBest.eq(true))
+        .all(db)
+        .await?;
+
+    let mut rounds: HashMap<String, (String, RoundScore)> = HashMap::new();
+
+    for guess in guesses {
+        let round_score = &mut rounds
+            .entry(guess.round_id.clone())
+            .or_insert_with(|| (
+                guess.game_id.clone(),
+                RoundScore {
+                    country_code: guess.round_country_code.clone(),
+                    score: 0,
+                    opponent_score: 0,
+                    distance: None,
+                    opponent_distance: None
+                }
+            ))
+            .1;
+
+        if team_ids.get(guess.game_id.as_str()) == Some(&guess.team_id.as_str()) {
+            round_score.score = guess.score;
+            round_score.distance = Some(guess.distance);
+        } else {
+            round_score.opponent_score = guess.score;
+            round_score.opponent_distance = Some(guess.distance);
+        }
+    }
+
+    let mut games_round_scores: HashMap<String, Vec<RoundScore>> = HashMap::new();
+
+    for (game_id, round_score) in rounds.into_values() {
+        games_round_scores.entry(game_id).or_default().push(round_score);
+    }
+
+    Ok(games_round_scores)
+}
+
+fn get_score_difference(round_scores: &[RoundScore]) -> i32 {
+    round_scores
+        .iter()
+        .map(|round_score| round_score.score - round_score.opponent_score)
+        .sum()
+}
+
+/// Opponent team id, team game mode, games, wins, losses, draws, rating change and last played.
+type OpponentGamesRow = (String, String, i64, i64, i64, i64, Option<i64>, DateTime<Utc>);
+
+/// Summarizes the games against each opponent team in `opponent_team_ids`, or against every
+/// opponent, most played opponents first.
+async fn get_opponent_summaries(
+    team_ids: &HashSet<String>,
+    opponent_team_ids: Option<&HashSet<String>>,
+    limit: usize,
+    db: &DatabaseConnection
+) -> Result<Vec<OpponentSummary>, Error> {
+    let health = player_team_expr(team_ids, duels_game::Column::HealthTeam1, duels_game::Column::HealthTeam2);
+    let opponent_health = opponent_team_expr(team_ids, duels_game::Column::HealthTeam1, duels_game::Column::HealthTeam2);
+    let rating_change = Expr::expr(player_team_expr(team_ids, duels_game::Column::RatingAfterTeam1, duels_game::Column::RatingAfterTeam2))
+        .sub(player_team_expr(team_ids, duels_game::Column::RatingBeforeTeam1, duels_game::Column::RatingBeforeTeam2));
+    let last_played = SimpleExpr::from(Func::max(start_time_expr(Expr::col((duels_game::Entity, duels_game::Column::StartTime)).into())));
+
+    let mut query = DuelsGame::find()
+        .select_only()
+        .column_as(opponent_expr(team_ids), "opponent_team_id")
+        .column_as(Expr::col((duels_game::Entity, duels_game::Column::TeamGameMode)).max(), "team_game_mode")
+        .column_as(Expr::col((duels_game::Entity, duels_game::Column::Id)).count(), "games")
+        .column_as(count_if(Expr::expr(health.clone()).gt(opponent_health.clone())), "wins")
+        .column_as(count_if(Expr::expr(health.clone()).lt(opponent_health.clone())), "losses")
+        .column_as(count_if(Expr::expr(health).eq(opponent_health)), "draws")
+        .column_as(SimpleExpr::from(Func::sum(rating_change)), "rating_change")
+        .column_as(last_played.clone(), "last_played")
+        .filter(is_player_game(team_ids));
+
+    if let Some(opponent_team_ids) = opponent_team_ids {
+        query = query.filter(Expr::expr(opponent_expr(team_ids)).is_in(opponent_team_ids.iter().cloned()));
+    }
+
+    let rows: Vec<OpponentGamesRow> = query
+        .group_by(opponent_team_id_column())
+        .order_by(Expr::col((duels_game::Entity, duels_game::Column::Id)).count(), Order::Desc)
+        .order_by(last_played, Order::Desc)
+        .order_by(opponent_team_id_column(), Order::Asc)
+        .limit(limit as u64)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let opponent_ids: Vec<String> = rows.iter().map(|row| row.0.clone()).collect();
+    let score_differences = get_score_differences(team_ids, &opponent_ids, db).await.map_err(ErrorInternalServerError)?;
+
+    let teams = rows
+        .iter()
+        .map(|row| Ok((row.0.clone(), TeamGameMode::from_str(&row.1).map_err(ErrorInternalServerError)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let teams_members = get_teams_members(&teams, db).await.map_err(ErrorInternalServerError)?;
+
+    let player_ids: Vec<String> = teams_members.values().flat_map(|(_, player_ids)| player_ids.iter().cloned()).collect();
+    let players = get_players(&player_ids, db).await.map_err(ErrorInternalServerError)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(opponent_team_id, team_game_mode, games, wins, losses, draws, rating_change, last_played)| {
+            let (name, opponent_ids) = teams_members.get(&opponent_team_id).cloned().unwrap_or_default();
+
+            let opponent_names = name
+                .into_iter()
+                .chain(opponent_ids.iter().filter_map(|opponent_id| players.get(opponent_id)).map(|opponent| opponent.name.clone()))
+                .collect();
+
+            let (score_difference, rounds) = score_differences.get(&opponent_team_id).copied().unwrap_or_default();
+
+            OpponentSummary {
+                opponent_team_id,
+                team_game_mode,
+                opponent_names,
+                games: games as usize,
+                wins: wins as usize,
+                losses: losses as usize,
+                draws: draws as usize,
+                avg_score_difference: (rounds > 0).then(|| score_difference as f64 / rounds as f64),
+                rating_change: rating_change.unwrap_or_default() as i32,
+                last_played: last_played.to_rfc3339()
+            }
+        })
+        .collect())
+}
+
+/// Sums the score differences of the teams' best guesses per opponent, as `(score_difference, rounds)`.
+async fn get_score_differences(
+    team_ids: &HashSet<String>,
+    opponent_team_ids: &[String],
+    db: &DatabaseConnection
+) -> Result<HashMap<String, (i64, i64)>, DbErr> {
+    if opponent_team_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let is_own_guess = Expr::col((guess::Entity, guess::Column::TeamId))
+        .eq(player_team_expr(team_ids, duels_game::Column::TeamId1, duels_game::Column::TeamId2));
+    let score = Expr::col((guess::Entity, guess::Column::Score));
+    let score_difference = Expr::case(is_own_guess, score.clone()).finally(score.mul(-1));
+
+    let rows: Vec<(String, Option<i64>, i64)> = Guess::find()
+        .select_only()
+        .column_as(opponent_expr(team_ids), "opponent_team_id")
+        .column_as(SimpleExpr::from(Func::sum(score_difference)), "score_difference")
+        .column_as(Expr::col((guess::Entity, guess::Column::RoundId)).count_distinct(), "rounds")
+        .join(JoinType::InnerJoin, guess::Relation::Game.def())
+        .filter(guess::Column::IsTeamsBest.eq(true))
+        .filter(is_player_game(team_ids))
+        .filter(Expr::expr(opponent_expr(team_ids)).is_in(opponent_team_ids.iter().cloned()))
+        .group_by(opponent_team_id_column())
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(opponent_team_id, score_difference, rounds)| (opponent_team_id, (score_difference.unwrap_or_default(), rounds)))
+        .collect())
+}
+
+fn get_country_comparisons(round_scores: Vec<&RoundScore>) -> Vec<CountryComparison> {
+    let mut countries: BTreeMap<&str, Vec<&RoundScore>> = BTreeMap::new();
+
+    for round_score in round_scores {
+        countries.entry(&round_score.country_code).or_default().push(round_score);
+    }
+
+    let mut comparisons: Vec<CountryComparison> = countries
+        .into_iter()
+        .map(|(country_code, rounds)| CountryComparison {
+            country_code: String::from(country_code),
+            rounds: rounds.len(),
+            rounds_won: rounds.iter().filter(|round| round.score > round.opponent_score).count(),
+            avg_score: average(rounds.iter().map(|round| round.score as f64)).unwrap_or_default(),
+            opponent_avg_score: average(rounds.iter().map(|round| round.opponent_score as f64)).unwrap_or_default(),
+            avg_distance: average(rounds.iter().filter_map(|round| round.distance)),
+            opponent_avg_distance: average(rounds.iter().filter_map(|round| round.opponent_distance))
+        })
+        .collect();
+
+    comparisons.sort_by_key(|comparison| Reverse(comparison.rounds));
+    comparisons
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpponentsQuery {
+    limit: Option<usize>
+}
+
+#[get("/stats/opponents")]
+pub async fn get_opponents_stats(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<OpponentsQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let db = db.get_ref();
+    let team_ids = get_session_team_ids(&http_request, db).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_OPPONENTS_LIMIT).clamp(1, MAX_OPPONENTS_LIMIT);
+
+    let opponents = get_opponent_summaries(&team_ids, None, limit, db).await?;
+
+    Ok(HttpResponse::Ok().json(OpponentsResponse { opponents }))
+}
+
+/// `opponent_id` is either a player id or a team id. For a player every team they played in counts.
+#[get("/stats/opponents/{opponent_id}")]
+pub async fn get_opponent_stats(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<String>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let db = db.get_ref();
+    let opponent_id = path.into_inner();
+    let team_ids = get_session_team_ids(&http_request, db).await?;
+
+    let mut opponent_team_ids: HashSet<String> = get_player_team_ids(&opponent_id, db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+    opponent_team_ids.insert(opponent_id);
+
+    let games: Vec<PlayerGame> = DuelsGame::find()
+        .filter(is_player_game(&team_ids))
+        .filter(Expr::expr(opponent_expr(&team_ids)).is_in(opponent_team_ids.iter().cloned()))
+        .order_by(start_time_expr(Expr::col((duels_game::Entity, duels_game::Column::StartTime)).into()), Order::Desc)
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| PlayerGame::new(game, &team_ids))
+        .collect::<Result<_, _>>()?;
+
+    if games.is_empty() {
+        return Err(ErrorNotFound("No games against this opponent!"));
+    }
+
+    let games: Vec<&PlayerGame> = games.iter().collect();
+    let round_scores = get_round_scores(&games, db).await.map_err(ErrorInternalServerError)?;
+    let opponents = get_opponent_summaries(&team_ids, Some(&opponent_team_ids), opponent_team_ids.len(), db).await?;
+
+    let head_to_head_games = games
+        .iter()
+        .map(|game| HeadToHeadGame {
+            id: game.game.id.clone(),
+            team_game_mode: game.game.team_game_mode.clone(),
+            start_time: game.game.start_time.clone(),
+            result: game.result(),
+            rating_change: game.rating_change(),
+            score_difference: round_scores.get(&game.game.id).map(|scores| get_score_difference(scores)).unwrap_or_default()
+        })
+        .collect();
+
+    let countries = get_country_comparisons(round_scores.values().flatten().collect());
+
+    let response = HeadToHeadResponse {
+        opponents,
+        games: head_to_head_games,
+        countries
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
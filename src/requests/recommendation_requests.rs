@@ -0,0 +1,221 @@
+use crate::entities::prelude::{DuelsRound, Guess};
+use crate::entities::{duels_round, guess};
+use crate::login::get_player_id_from_session;
+use crate::requests::game_requests::PlayerGame;
+use crate::requests::{average, get_player_team_ids, GameFilter};
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const DEFAULT_LAST_GAMES: usize = 20;
+const DEFAULT_RECOMMENDATION_LIMIT: usize = 10;
+const MAX_RECOMMENDATION_LIMIT: usize = 100;
+/// Maximum rating difference for an opponent to count as similarly rated.
+const SIMILAR_RATING_RANGE: i32 = 100;
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+enum Baseline {
+    /// The player's own average score over every country.
+    #[default]
+    Own,
+    /// The average score of similarly rated opponents in the same country.
+    SimilarOpponents
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendationQuery {
+    #[serde(default)]
+    baseline: Baseline,
+    last_games: Option<usize>,
+    limit: Option<usize>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountryRecommendation {
+    country_code: String,
+    rounds: usize,
+    /// Share of all rounds that were played in this country.
+    frequency: f64,
+    avg_score: f64,
+    baseline_score: f64,
+    deficit: f64,
+    /// `frequency * deficit`, the points lost on an average round by being weak in this country.
+    expected_points_lost: f64,
+    recent_rounds: usize,
+    recent_avg_score: Option<f64>,
+    /// Recent average score minus the overall average score in this country.
+    trend: Option<f64>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecommendationsResponse {
+    baseline: Baseline,
+    rounds: usize,
+    avg_score: Option<f64>,
+    last_games: usize,
+    recommendations: Vec<CountryRecommendation>
+}
+
+/// Best scores of both teams on a round. A team that did not guess scored 0.
+struct RoundScore<'a> {
+    country_code: &'a str,
+    score: i32,
+    opponent_score: i32,
+    is_similar_opponent: bool,
+    is_recent: bool
+}
+
+fn is_similar_opponent(game: &PlayerGame) -> bool {
+    match (game.rating_before, game.opponent_rating_before) {
+        (Some(rating), Some(opponent_rating)) => (rating - opponent_rating).abs() <= SIMILAR_RATING_RANGE,
+        _ => false
+    }
+}
+
+fn get_recommendation(
+    country_code: &str,
+    rounds: &[&RoundScore],
+    total_rounds: usize,
+    avg_score: f64,
+    baseline: Baseline
+) -> CountryRecommendation {
+    let country_avg_score = average(rounds.iter().map(|round| round.score as f64)).unwrap_or_default();
+
+    let baseline_score = match baseline {
+        Baseline::Own => avg_score,
+        Baseline::SimilarOpponents => average(
+            rounds
+                .iter()
+                .filter(|round| round.is_similar_opponent)
+                .map(|round| round.opponent_score as f64)
+        ).unwrap_or(avg_score)
+    };
+
+    let frequency = rounds.len() as f64 / total_rounds as f64;
+    let deficit = baseline_score - country_avg_score;
+
+    let recent_scores: Vec<f64> = rounds
+        .iter()
+        .filter(|round| round.is_recent)
+        .map(|round| round.score as f64)
+        .collect();
+    let recent_avg_score = average(recent_scores.iter().copied());
+
+    CountryRecommendation {
+        country_code: String::from(country_code),
+        rounds: rounds.len(),
+        frequency,
+        avg_score: country_avg_score,
+        baseline_score,
+        deficit,
+        expected_points_lost: frequency * deficit,
+        recent_rounds: recent_scores.len(),
+        recent_avg_score,
+        trend: recent_avg_score.map(|recent_avg_score| recent_avg_score - country_avg_score)
+    }
+}
+
+#[get("/stats/recommendations")]
+pub async fn get_recommendations(
+    db: web::Data<DatabaseConnection>,
+    filter: web::Query<GameFilter>,
+    query: web::Query<RecommendationQuery>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let query = query.into_inner();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+    let team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+
+    let last_games = query.last_games.unwrap_or(DEFAULT_LAST_GAMES);
+    let limit = query.limit.unwrap_or(DEFAULT_RECOMMENDATION_LIMIT).clamp(1, MAX_RECOMMENDATION_LIMIT);
+
+    let mut games: Vec<PlayerGame> = filter
+        .find_games(&team_ids, db)
+        .await?
+        .into_iter()
+        .map(|game| PlayerGame::new(game, &team_ids))
+        .collect::<Result<_, _>>()?;
+
+    games.sort_unstable_by_key(|game| Reverse(game.start_time));
+
+    let recent_game_ids: HashSet<&str> = games.iter().take(last_games).map(|game| game.game.id.as_str()).collect();
+    let games_by_id: HashMap<&str, &PlayerGame> = games.iter().map(|game| (game.game.id.as_str(), game)).collect();
+
+    let rounds = DuelsRound::find()
+        .filter(duels_round::Column::GameId.is_in(games_by_id.keys().copied()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(games_by_id.keys().copied()))
+        .filter(guess::Column::IsTeamsBest.eq(true))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut best_scores: HashMap<(&str, &str), i32> = HashMap::new();
+
+    for guess in &guesses {
+        let best_score = best_scores.entry((&guess.round_id, &guess.team_id)).or_default();
+        *best_score = (*best_score).max(guess.score);
+    }
+
+    let round_scores: Vec<RoundScore> = rounds
+        .iter()
+        .filter_map(|round| {
+            let game = games_by_id.get(round.game_id.as_str())?;
+
+            Some(RoundScore {
+                country_code: &round.round_country_code,
+                score: best_scores.get(&(round.id.as_str(), game.team_id.as_str())).copied().unwrap_or_default(),
+                opponent_score: best_scores.get(&(round.id.as_str(), game.opponent_team_id.as_str())).copied().unwrap_or_default(),
+                is_similar_opponent: is_similar_opponent(game),
+                is_recent: recent_game_ids.contains(round.game_id.as_str())
+            })
+        })
+        .collect();
+
+    let avg_score = average(round_scores.iter().map(|round| round.score as f64));
+    let mut countries: BTreeMap<&str, Vec<&RoundScore>> = BTreeMap::new();
+
+    for round_score in &round_scores {
+        countries.entry(round_score.country_code).or_default().push(round_score);
+    }
+
+    let mut recommendations: Vec<CountryRecommendation> = countries
+        .into_iter()
+        .map(|(country_code, rounds)| {
+            get_recommendation(country_code, &rounds, round_scores.len(), avg_score.unwrap_or_default(), query.baseline)
+        })
+        .filter(|recommendation| recommendation.expected_points_lost > 0.0)
+        .collect();
+
+    recommendations.sort_by(|a, b| b.expected_points_lost.total_cmp(&a.expected_points_lost));
+    recommendations.truncate(limit);
+
+    let response = RecommendationsResponse {
+        baseline: query.baseline,
+        rounds: round_scores.len(),
+        avg_score,
+        last_games: recent_game_ids.len(),
+        recommendations
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
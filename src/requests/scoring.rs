@@ -0,0 +1,77 @@
+//! GeoGuessr's scoring curve, used for guesses GeoGuessr did not report a score for.
+
+pub const MAX_SCORE: i32 = 5000;
+
+/// Guesses closer than this many meters to the location always get the full score.
+const PERFECT_SCORE_DISTANCE: f64 = 25.0;
+
+/// Score of a guess `distance` meters away on a map with the given max error distance.
+/// The max error distance is the map's `maxErrorDistance`, not the diagonal of the game's bounds.
+pub fn get_score(distance: f64, max_error_distance: f64) -> i32 {
+    if distance <= PERFECT_SCORE_DISTANCE {
+        return MAX_SCORE;
+    }
+
+    if max_error_distance <= 0.0 {
+        return 0;
+    }
+
+    (MAX_SCORE as f64 * (-10.0 * distance / max_error_distance).exp()).round() as i32
+}
+
+/// Distance in meters between the corners of a map's bounds. Only used as max error distance
+/// if the map's real one is unknown.
+pub fn get_bounds_diagonal(min_lat: f64, min_lng: f64, max_lat: f64, max_lng: f64) -> f64 {
+    let a = geoutils::Location::new(min_lat, min_lng);
+    let b = geoutils::Location::new(max_lat, max_lng);
+
+    a.distance_to(&b).unwrap().meters()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geo_guessr::DuelsGame;
+
+    const DUELS_GAME: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/duels_game.json"));
+
+    #[test]
+    fn close_guesses_get_the_full_score() {
+        assert_eq!(get_score(0.0, 1000.0), MAX_SCORE);
+        assert_eq!(get_score(25.0, 1000.0), MAX_SCORE);
+        assert!(get_score(26.0, 1000.0) < MAX_SCORE);
+    }
+
+    #[test]
+    fn matches_reported_scores() {
+        let game: DuelsGame = serde_json::from_str(DUELS_GAME).unwrap();
+        let max_error_distance = game.options.map.max_error_distance as f64;
+
+        let reported_guesses: Vec<(f64, i32)> = game
+            .teams
+            .iter()
+            .flat_map(|team| team.players.iter())
+            .flat_map(|player| player.guesses.iter())
+            .filter_map(|guess| guess.score.map(|score| (guess.distance, score)))
+            .collect();
+
+        assert!(!reported_guesses.is_empty());
+
+        for (distance, score) in reported_guesses {
+            assert_eq!(get_score(distance, max_error_distance), score, "guess {} m away", distance);
+        }
+    }
+
+    #[test]
+    fn score_depends_on_the_map_size() {
+        assert!(get_score(50_000.0, 1_000_000.0) < get_score(50_000.0, 10_000_000.0));
+        assert_eq!(get_score(50_000.0, 0.0), 0);
+    }
+
+    #[test]
+    fn measures_bounds_diagonal() {
+        // One degree of longitude along the equator.
+        assert!((get_bounds_diagonal(0.0, 0.0, 0.0, 1.0) - 111_319.5).abs() < 1.0);
+        assert_eq!(get_bounds_diagonal(10.0, 10.0, 10.0, 10.0), 0.0);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::entities::prelude::SoloGame;
+use crate::entities::solo_game;
+use crate::login::get_player_id_from_session;
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+const BEST_STREAKS: usize = 10;
+const COMMON_BREAKING_LOCATIONS: usize = 10;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BestStreak {
+    game_id: String,
+    map_id: String,
+    start_time: String,
+    streak_length: i32,
+    streak_end_location_code: Option<String>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BreakingLocation {
+    /// Country code, or US state code for US state streaks.
+    location_code: String,
+    streaks_ended: usize
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StreakTypeStats {
+    streak_type: String,
+    games: usize,
+    avg_length: f64,
+    best_streaks: Vec<BestStreak>,
+    common_breaking_locations: Vec<BreakingLocation>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StreaksResponse {
+    streak_types: Vec<StreakTypeStats>
+}
+
+fn get_streak_type_stats(streak_type: String, games: Vec<solo_game::Model>) -> StreakTypeStats {
+    let lengths: Vec<i32> = games.iter().filter_map(|game| game.streak_length).collect();
+    let mut breaking_locations: HashMap<&str, usize> = HashMap::new();
+
+    for location_code in games.iter().filter_map(|game| game.streak_end_location_code.as_deref()) {
+        *breaking_locations.entry(location_code).or_default() += 1;
+    }
+
+    let mut common_breaking_locations: Vec<BreakingLocation> = breaking_locations
+        .into_iter()
+        .map(|(location_code, streaks_ended)| BreakingLocation {
+            location_code: String::from(location_code),
+            streaks_ended
+        })
+        .collect();
+
+    common_breaking_locations.sort_by_key(|location| Reverse(location.streaks_ended));
+    common_breaking_locations.truncate(COMMON_BREAKING_LOCATIONS);
+
+    let games_count = games.len();
+    let mut best_streaks: Vec<BestStreak> = games
+        .into_iter()
+        .map(|game| BestStreak {
+            game_id: game.id,
+            map_id: game.map_id,
+            start_time: game.start_time,
+            streak_length: game.streak_length.unwrap_or_default(),
+            streak_end_location_code: game.streak_end_location_code
+        })
+        .collect();
+
+    best_streaks.sort_by_key(|streak| Reverse(streak.streak_length));
+    best_streaks.truncate(BEST_STREAKS);
+
+    StreakTypeStats {
+        streak_type,
+        games: games_count,
+        avg_length: lengths.iter().sum::<i32>() as f64 / lengths.len().max(1) as f64,
+        best_streaks,
+        common_breaking_locations
+    }
+}
+
+#[get("/stats/streaks")]
+pub async fn get_streak_stats(
+    db: web::Data<DatabaseConnection>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+
+    let games = SoloGame::find()
+        .filter(solo_game::Column::PlayerId.eq(&player_id))
+        .filter(solo_game::Column::StreakType.is_not_null())
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut streak_types: BTreeMap<String, Vec<solo_game::Model>> = BTreeMap::new();
+
+    for game in games {
+        if let Some(streak_type) = game.streak_type.clone() {
+            streak_types.entry(streak_type).or_default().push(game);
+        }
+    }
+
+    let response = StreaksResponse {
+        streak_types: streak_types
+            .into_iter()
+            .map(|(streak_type, games)| get_streak_type_stats(streak_type, games))
+            .collect()
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
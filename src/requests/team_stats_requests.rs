@@ -0,0 +1,290 @@
+use crate::entities::prelude::{CompTeam, DuelsGame, FunTeam, Guess};
+use crate::entities::{comp_team, duels_game, fun_team, guess, player};
+use crate::login::get_player_id_from_session;
+use crate::requests::game_requests::{get_players, GameResult, PlayerGame};
+use crate::requests::get_player_team_ids;
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum TeamKind {
+    Comp,
+    Fun
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TeammateCountryStats {
+    player_id: String,
+    guesses: usize,
+    avg_score: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamCountryStats {
+    country_code: String,
+    rounds: usize,
+    /// Average score of the team's best guess.
+    avg_best_score: f64,
+    /// Average score of every guess of the team.
+    avg_score: f64,
+    /// How many points guessing as a team adds on average over a single guess.
+    coverage_gain: f64,
+    teammates: Vec<TeammateCountryStats>,
+    /// Teammate with the highest average score, `None` if nobody guessed with a known player id.
+    stronger_player_id: Option<String>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TeammateStats {
+    player_id: String,
+    guesses: usize,
+    avg_score: f64,
+    best_guesses: usize,
+    /// Share of the team's rounds where this teammate had the team's best guess.
+    best_guess_rate: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamStats {
+    team_id: String,
+    kind: TeamKind,
+    name: Option<String>,
+    rating: Option<i32>,
+    players: Vec<player::Model>,
+    games: usize,
+    wins: usize,
+    losses: usize,
+    draws: usize,
+    rating_change: i32,
+    teammates: Vec<TeammateStats>,
+    countries: Vec<TeamCountryStats>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamsResponse {
+    teams: Vec<TeamStats>
+}
+
+struct TeamGuess {
+    player_id: Option<String>,
+    score: i32,
+    is_teams_best: bool
+}
+
+/// All guesses of one team on one round.
+#[derive(Default)]
+struct TeamRound {
+    country_code: String,
+    guesses: Vec<TeamGuess>
+}
+
+fn get_teammate_averages<'a, I: Iterator<Item = &'a TeamRound>>(rounds: I) -> BTreeMap<&'a str, (usize, f64)> {
+    let mut teammates: BTreeMap<&str, (usize, f64)> = BTreeMap::new();
+
+    for guess in rounds.flat_map(|round| round.guesses.iter()) {
+        if let Some(player_id) = &guess.player_id {
+            let (guesses, score_sum) = teammates.entry(player_id).or_default();
+            *guesses += 1;
+            *score_sum += guess.score as f64;
+        }
+    }
+
+    teammates
+        .into_iter()
+        .map(|(player_id, (guesses, score_sum))| (player_id, (guesses, score_sum / guesses as f64)))
+        .collect()
+}
+
+fn get_teammate_stats(rounds: &[TeamRound]) -> Vec<TeammateStats> {
+    get_teammate_averages(rounds.iter())
+        .into_iter()
+        .map(|(player_id, (guesses, avg_score))| {
+            let best_guesses = rounds
+                .iter()
+                .flat_map(|round| round.guesses.iter())
+                .filter(|guess| guess.is_teams_best && guess.player_id.as_deref() == Some(player_id))
+                .count();
+
+            TeammateStats {
+                player_id: String::from(player_id),
+                guesses,
+                avg_score,
+                best_guesses,
+                best_guess_rate: best_guesses as f64 / rounds.len() as f64
+            }
+        })
+        .collect()
+}
+
+fn get_country_stats(rounds: &[TeamRound]) -> Vec<TeamCountryStats> {
+    let mut countries: BTreeMap<String, Vec<&TeamRound>> = BTreeMap::new();
+
+    for round in rounds {
+        countries.entry(round.country_code.clone()).or_default().push(round);
+    }
+
+    let mut country_stats: Vec<TeamCountryStats> = countries
+        .into_iter()
+        .map(|(country_code, rounds)| {
+            let best_scores: Vec<f64> = rounds
+                .iter()
+                .map(|round| round.guesses.iter().map(|guess| guess.score).max().unwrap() as f64)
+                .collect();
+            let scores: Vec<f64> = rounds
+                .iter()
+                .flat_map(|round| round.guesses.iter().map(|guess| guess.score as f64))
+                .collect();
+
+            let teammates: Vec<TeammateCountryStats> = get_teammate_averages(rounds.iter().copied())
+                .into_iter()
+                .map(|(player_id, (guesses, avg_score))| TeammateCountryStats {
+                    player_id: String::from(player_id),
+                    guesses,
+                    avg_score
+                })
+                .collect();
+
+            let stronger_player_id = teammates
+                .iter()
+                .max_by(|a, b| a.avg_score.total_cmp(&b.avg_score))
+                .map(|teammate| teammate.player_id.clone());
+
+            let avg_best_score = best_scores.iter().sum::<f64>() / best_scores.len() as f64;
+            let avg_score = scores.iter().sum::<f64>() / scores.len() as f64;
+
+            TeamCountryStats {
+                country_code,
+                rounds: rounds.len(),
+                avg_best_score,
+                avg_score,
+                coverage_gain: avg_best_score - avg_score,
+                teammates,
+                stronger_player_id
+            }
+        })
+        .collect();
+
+    country_stats.sort_by_key(|stats| Reverse(stats.rounds));
+    country_stats
+}
+
+#[get("/stats/teams")]
+pub async fn get_teams_stats(
+    db: web::Data<DatabaseConnection>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+
+    let mut team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+    team_ids.remove(&player_id);
+
+    let comp_teams = CompTeam::find()
+        .filter(comp_team::Column::TeamId.is_in(&team_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let fun_teams = FunTeam::find()
+        .filter(fun_team::Column::TeamId.is_in(&team_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let games: Vec<PlayerGame> = DuelsGame::find()
+        .filter(duels_game::Column::TeamId1.is_in(&team_ids).or(duels_game::Column::TeamId2.is_in(&team_ids)))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .into_iter()
+        .map(|game| PlayerGame::new(game, &team_ids))
+        .collect::<Result<_, _>>()?;
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(games.iter().map(|game| game.game.id.clone())))
+        .filter(guess::Column::TeamId.is_in(&team_ids))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut teams_rounds: HashMap<String, HashMap<String, TeamRound>> = HashMap::new();
+
+    for guess in guesses {
+        let round = teams_rounds
+            .entry(guess.team_id)
+            .or_default()
+            .entry(guess.round_id)
+            .or_default();
+
+        round.country_code = guess.round_country_code;
+        round.guesses.push(TeamGuess {
+            player_id: guess.player_id,
+            score: guess.score,
+            is_teams_best: guess.is_teams_best
+        });
+    }
+
+    let mut teams_games: HashMap<&str, Vec<&PlayerGame>> = HashMap::new();
+
+    for game in &games {
+        teams_games.entry(&game.team_id).or_default().push(game);
+    }
+
+    let team_values = comp_teams
+        .into_iter()
+        .map(|team| (team.team_id, TeamKind::Comp, Some(team.name), team.rating, vec![team.player_id1, team.player_id2]))
+        .chain(
+            fun_teams
+                .into_iter()
+                .map(|team| (team.team_id, TeamKind::Fun, None, None, team.player_ids))
+        );
+
+    let mut seen_team_ids = HashSet::new();
+    let mut teams = Vec::new();
+
+    for (team_id, kind, name, rating, player_ids) in team_values {
+        if !seen_team_ids.insert(team_id.clone()) {
+            continue;
+        }
+
+        let games = teams_games.remove(team_id.as_str()).unwrap_or_default();
+        let rounds: Vec<TeamRound> = teams_rounds.remove(&team_id).unwrap_or_default().into_values().collect();
+        let mut players = get_players(&player_ids, db).await.map_err(ErrorInternalServerError)?;
+
+        teams.push(TeamStats {
+            kind,
+            name,
+            rating,
+            players: player_ids.iter().filter_map(|player_id| players.remove(player_id)).collect(),
+            games: games.len(),
+            wins: games.iter().filter(|game| game.result() == GameResult::Win).count(),
+            losses: games.iter().filter(|game| game.result() == GameResult::Loss).count(),
+            draws: games.iter().filter(|game| game.result() == GameResult::Draw).count(),
+            rating_change: games.iter().filter_map(|game| game.rating_change()).sum(),
+            teammates: get_teammate_stats(&rounds),
+            countries: get_country_stats(&rounds),
+            team_id
+        });
+    }
+
+    teams.sort_by_key(|team| Reverse(team.games));
+
+    Ok(HttpResponse::Ok().json(TeamsResponse { teams }))
+}
@@ -0,0 +1,236 @@
+use crate::entities::prelude::{DuelsRound, Guess};
+use crate::entities::{duels_game, duels_round, guess};
+use crate::login::get_player_id_from_session;
+use crate::requests::guess_order_requests::get_guess_order;
+use crate::requests::{average, get_player_team_ids, GameFilter};
+use actix_web::error::{ErrorInternalServerError, ErrorUnauthorized};
+use actix_web::{get, web, Error, HttpRequest, HttpResponse, Responder};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+/// Lower bounds of the time buckets in seconds, the last bucket is open ended.
+const TIME_BUCKETS: [i32; 8] = [0, 10, 20, 30, 45, 60, 90, 120];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimeBucket {
+    min_time: i32,
+    /// Exclusive upper bound, `None` for the last bucket.
+    max_time: Option<i32>,
+    guesses: usize,
+    avg_score: Option<f64>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CountryTiming {
+    country_code: String,
+    guesses: usize,
+    avg_time: f64,
+    avg_score: f64
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimingStats {
+    /// `None` for the stats over every geo mode.
+    geo_mode: Option<String>,
+    rounds: usize,
+    guesses: usize,
+    /// Rounds the player did not guess on.
+    timeouts: usize,
+    timeout_rate: f64,
+    avg_time: Option<f64>,
+    /// Share of rounds where the player's team guessed before the opponent.
+    guessed_first_rate: f64,
+    avg_score_guessed_first: Option<f64>,
+    avg_score_guessed_second: Option<f64>,
+    time_buckets: Vec<TimeBucket>,
+    countries: Vec<CountryTiming>
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TimingResponse {
+    overall: TimingStats,
+    geo_modes: Vec<TimingStats>
+}
+
+/// The player's guess on a round and whether their team guessed before the opponent.
+#[derive(Clone, Copy)]
+struct RoundTiming<'a> {
+    country_code: &'a str,
+    guess: Option<&'a guess::Model>,
+    guessed_first: bool
+}
+
+fn get_time_buckets(rounds: &[RoundTiming]) -> Vec<TimeBucket> {
+    TIME_BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(index, &min_time)| {
+            let max_time = TIME_BUCKETS.get(index + 1).copied();
+            let scores: Vec<f64> = rounds
+                .iter()
+                .filter_map(|round| round.guess)
+                .filter(|guess| {
+                    guess.time.is_some_and(|time| time >= min_time && max_time.is_none_or(|max_time| time < max_time))
+                })
+                .map(|guess| guess.score as f64)
+                .collect();
+
+            TimeBucket {
+                min_time,
+                max_time,
+                guesses: scores.len(),
+                avg_score: average(scores.into_iter())
+            }
+        })
+        .collect()
+}
+
+fn get_country_timings(rounds: &[RoundTiming]) -> Vec<CountryTiming> {
+    let mut countries: BTreeMap<&str, Vec<(i32, i32)>> = BTreeMap::new();
+
+    for round in rounds {
+        if let Some((time, score)) = round.guess.and_then(|guess| guess.time.map(|time| (time, guess.score))) {
+            countries.entry(round.country_code).or_default().push((time, score));
+        }
+    }
+
+    let mut country_timings: Vec<CountryTiming> = countries
+        .into_iter()
+        .map(|(country_code, guesses)| CountryTiming {
+            country_code: String::from(country_code),
+            guesses: guesses.len(),
+            avg_time: average(guesses.iter().map(|(time, _)| *time as f64)).unwrap_or_default(),
+            avg_score: average(guesses.iter().map(|(_, score)| *score as f64)).unwrap_or_default()
+        })
+        .collect();
+
+    country_timings.sort_by_key(|country_timing| Reverse(country_timing.guesses));
+    country_timings
+}
+
+fn summarize_rounds(geo_mode: Option<String>, rounds: &[RoundTiming]) -> TimingStats {
+    let guesses: Vec<&guess::Model> = rounds.iter().filter_map(|round| round.guess).collect();
+    let timeouts = rounds.len() - guesses.len();
+    let guessed_first = rounds.iter().filter(|round| round.guessed_first).count();
+
+    let avg_score = |guessed_first: bool| average(
+        rounds
+            .iter()
+            .filter(|round| round.guessed_first == guessed_first)
+            .filter_map(|round| round.guess)
+            .map(|guess| guess.score as f64)
+    );
+
+    TimingStats {
+        geo_mode,
+        rounds: rounds.len(),
+        guesses: guesses.len(),
+        timeouts,
+        timeout_rate: timeouts as f64 / rounds.len().max(1) as f64,
+        avg_time: average(guesses.iter().filter_map(|guess| guess.time).map(|time| time as f64)),
+        guessed_first_rate: guessed_first as f64 / rounds.len().max(1) as f64,
+        avg_score_guessed_first: avg_score(true),
+        avg_score_guessed_second: avg_score(false),
+        time_buckets: get_time_buckets(rounds),
+        countries: get_country_timings(rounds)
+    }
+}
+
+/// Picks the player's own guess on a round, or the team's best guess if the guesses have no player id yet.
+fn get_player_guess<'a>(guesses: &[&'a guess::Model], player_id: &str) -> Option<&'a guess::Model> {
+    guesses
+        .iter()
+        .find(|guess| guess.player_id.as_deref() == Some(player_id))
+        .or_else(|| guesses.iter().find(|guess| guess.player_id.is_none() && guess.is_teams_best))
+        .copied()
+}
+
+fn get_round_timing<'a>(
+    round: &'a duels_round::Model,
+    team_id: &str,
+    guesses: &[&'a guess::Model],
+    player_id: &str
+) -> RoundTiming<'a> {
+    let team_guesses: Vec<&guess::Model> = guesses.iter().copied().filter(|guess| guess.team_id == team_id).collect();
+
+    RoundTiming {
+        country_code: &round.round_country_code,
+        guess: get_player_guess(&team_guesses, player_id),
+        guessed_first: get_guess_order(guesses).is_some_and(|guess_order| guess_order.first_team_id == team_id)
+    }
+}
+
+#[get("/stats/timing")]
+pub async fn get_timing_stats(
+    db: web::Data<DatabaseConnection>,
+    filter: web::Query<GameFilter>,
+    http_request: HttpRequest
+) -> Result<impl Responder, Error> {
+    let session_id = match http_request.cookie("sessionId") {
+        Some(cookie) => {
+            String::from(cookie.value())
+        },
+        None => return Err(ErrorUnauthorized("Missing `sessionId` cookie!"))
+    };
+
+    let db = db.get_ref();
+    let player_id = get_player_id_from_session(&session_id, db).await?;
+    let team_ids = get_player_team_ids(&player_id, db).await.map_err(ErrorInternalServerError)?;
+
+    let games: HashMap<String, duels_game::Model> = filter
+        .find_games(&team_ids, db)
+        .await?
+        .into_iter()
+        .map(|game| (game.id.clone(), game))
+        .collect();
+
+    let rounds = DuelsRound::find()
+        .filter(duels_round::Column::GameId.is_in(games.keys()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let guesses = Guess::find()
+        .filter(guess::Column::GameId.is_in(games.keys()))
+        .all(db)
+        .await
+        .map_err(ErrorInternalServerError)?;
+
+    let mut round_guesses: HashMap<&str, Vec<&guess::Model>> = HashMap::new();
+
+    for guess in &guesses {
+        round_guesses.entry(&guess.round_id).or_default().push(guess);
+    }
+
+    let mut all_rounds = Vec::with_capacity(rounds.len());
+    let mut geo_mode_rounds: BTreeMap<&str, Vec<RoundTiming>> = BTreeMap::new();
+
+    for round in &rounds {
+        let Some(game) = games.get(&round.game_id) else {
+            continue;
+        };
+
+        let team_id = if team_ids.contains(&game.team_id1) { &game.team_id1 } else { &game.team_id2 };
+        let guesses = round_guesses.get(round.id.as_str()).map(Vec::as_slice).unwrap_or_default();
+
+        let round_timing = get_round_timing(round, team_id, guesses, &player_id);
+        all_rounds.push(round_timing);
+        geo_mode_rounds.entry(&game.geo_mode).or_default().push(round_timing);
+    }
+
+    let response = TimingResponse {
+        overall: summarize_rounds(None, &all_rounds),
+        geo_modes: geo_mode_rounds
+            .into_iter()
+            .map(|(geo_mode, rounds)| summarize_rounds(Some(String::from(geo_mode)), &rounds))
+            .collect()
+    };
+
+    Ok(HttpResponse::Ok().json(response))
+}
@@ -0,0 +1,1106 @@
+//! Pure transformation of deserialized GeoGuessr games into database models.
+//!
+//! Nothing in here talks to GeoGuessr or the database. Player and team metadata has to be
+//! resolved beforehand and is passed in as [`ResolvedMetadata`].
+
+use crate::entities::br_game::ActiveModel as BrGameModel;
+use crate::entities::br_guess::ActiveModel as BrGuessModel;
+use crate::entities::br_player::ActiveModel as BrPlayerModel;
+use crate::entities::br_round::ActiveModel as BrRoundModel;
+use crate::entities::comp_team::ActiveModel as CompTeamModel;
+use crate::entities::duels_game::ActiveModel as DuelsGameModel;
+use crate::entities::duels_round::ActiveModel as DuelsRoundModel;
+use crate::entities::fun_team::ActiveModel as FunTeamModel;
+use crate::entities::guess::ActiveModel as GuessModel;
+use crate::entities::location::ActiveModel as LocationModel;
+use crate::entities::map::ActiveModel as MapModel;
+use crate::entities::player::ActiveModel as PlayerModel;
+use crate::entities::round_result::ActiveModel as RoundResultModel;
+use crate::entities::solo_game::ActiveModel as SoloGameModel;
+use crate::entities::solo_round::ActiveModel as SoloRoundModel;
+use crate::geo_guessr::GeoMode::{Moving, NoMove, NoMovingZooming, NoPanning, NoPanningMoving, NoPanningZooming, NoZooming, NMPZ};
+use crate::geo_guessr::{BattleRoyaleGame, BattleRoyaleGuess, BattleRoyaleMode, BattleRoyalePlayer, BattleRoyaleRound, DuelsGame, DuelsGuess, GeoMode, MovementOption, SoloGame, StreakType, Team, TeamGameMode};
+use crate::requests::geocoder::Geocoder;
+use crate::requests::scoring::{get_bounds_diagonal, get_score};
+use crate::requests::{BattleRoyaleGameData, GameData, SoloGameData};
+use actix_web::error::ErrorBadRequest;
+use actix_web::Error;
+use chrono::{DateTime, TimeDelta, Utc};
+use sea_orm::ActiveValue;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Player and team models that had to be fetched or looked up before a game can be transformed.
+#[derive(Default)]
+pub struct ResolvedMetadata {
+    pub players: Vec<PlayerModel>,
+    pub comp_teams: Vec<CompTeamModel>,
+    pub fun_teams: Vec<FunTeamModel>
+}
+
+pub fn get_team_id(mut player_ids: Vec<&str>) -> String {
+    player_ids.sort_unstable();
+    player_ids.join("-")
+}
+
+pub fn get_game_mode(team1_size: usize, team2_size: usize, is_rated: bool) -> TeamGameMode {
+    if team1_size == 1 && team2_size == 1 {
+        if is_rated {
+            TeamGameMode::DuelsRanked
+        } else {
+            TeamGameMode::Duels
+        }
+    } else if team1_size == 2 && team2_size == 2 {
+        if is_rated {
+            TeamGameMode::TeamDuelsRanked
+        } else {
+            TeamGameMode::TeamDuels
+        }
+    } else {
+        TeamGameMode::TeamFun
+    }
+}
+
+pub fn get_geo_mode(movement_options: &MovementOption) -> GeoMode {
+    match movement_options {
+        MovementOption {
+            forbid_moving: false,
+            forbid_zooming: false,
+            forbid_rotating: false
+        } => Moving,
+        MovementOption {
+            forbid_moving: false,
+            forbid_zooming: false,
+            forbid_rotating: true
+        } => NoPanning,
+        MovementOption {
+            forbid_moving: false,
+            forbid_zooming: true,
+            forbid_rotating: false
+        } => NoZooming,
+        MovementOption {
+            forbid_moving: false,
+            forbid_zooming: true,
+            forbid_rotating: true
+        } => NoPanningZooming,
+        MovementOption {
+            forbid_moving: true,
+            forbid_zooming: false,
+            forbid_rotating: false
+        } => NoMove,
+        MovementOption {
+            forbid_moving: true,
+            forbid_zooming: false,
+            forbid_rotating: true
+        } => NoPanningMoving,
+        MovementOption {
+            forbid_moving: true,
+            forbid_zooming: true,
+            forbid_rotating: false
+        } => NoMovingZooming,
+        MovementOption {
+            forbid_moving: true,
+            forbid_zooming: true,
+            forbid_rotating: true
+        } => NMPZ,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn get_duels_game_model(
+    game: &DuelsGame,
+    start_time: String,
+    game_mode: &TeamGameMode,
+    geo_mode: &GeoMode,
+    team_id1: String,
+    team_id2: String,
+    ratings_team1: (Option<i32>, Option<i32>),
+    ratings_team2: (Option<i32>, Option<i32>),
+) -> DuelsGameModel {
+    DuelsGameModel {
+        id: ActiveValue::Set(game.game_id.clone()),
+        team_id1: ActiveValue::Set(team_id1),
+        team_id2: ActiveValue::Set(team_id2),
+        health_team1: ActiveValue::Set(game.teams[0].health),
+        health_team2: ActiveValue::Set(game.teams[1].health),
+        team_game_mode: ActiveValue::Set(game_mode.to_string()),
+        geo_mode: ActiveValue::Set(geo_mode.to_string()),
+        start_time: ActiveValue::Set(start_time),
+        map_id: ActiveValue::Set(game.options.map.slug.clone()),
+        rating_before_team1: ActiveValue::Set(ratings_team1.0),
+        rating_before_team2: ActiveValue::Set(ratings_team2.0),
+        rating_after_team1: ActiveValue::Set(ratings_team1.1),
+        rating_after_team2: ActiveValue::Set(ratings_team2.1)
+    }
+}
+
+/// Rating before and after the game, taken from the first player of the team.
+pub fn get_team_ratings(team: &Team, game_mode: &TeamGameMode) -> (Option<i32>, Option<i32>) {
+    let Some(progress) = &team.players[0].progress_change else {
+        return (None, None);
+    };
+
+    match game_mode {
+        TeamGameMode::DuelsRanked | TeamGameMode::Duels => match &progress.ranked_system_progress {
+            Some(ranked_progress) => (ranked_progress.rating_before, ranked_progress.rating_after),
+            None => (None, None)
+        },
+        TeamGameMode::TeamDuelsRanked => match &progress.ranked_team_duels_progress {
+            Some(ranked_team_progress) => (ranked_team_progress.rating_before, ranked_team_progress.rating_after),
+            None => (None, None)
+        },
+        TeamGameMode::TeamDuels | TeamGameMode::TeamFun => (None, None)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn get_location_model(
+    pano_id: &str,
+    lat: f64,
+    lng: f64,
+    heading: f64,
+    pitch: f64,
+    zoom: f64,
+    country_code: &str,
+    geocoder: &dyn Geocoder
+) -> LocationModel {
+    LocationModel {
+        id: ActiveValue::Set(String::from(pano_id)),
+        lat: ActiveValue::Set(lat),
+        lng: ActiveValue::Set(lng),
+        heading: ActiveValue::Set(heading),
+        pitch: ActiveValue::Set(pitch),
+        zoom: ActiveValue::Set(zoom),
+        country_code: ActiveValue::Set(country_code.to_ascii_uppercase()),
+        subdivision_code: ActiveValue::Set(geocoder.subdivision_code(lat, lng))
+    }
+}
+
+/// Parses a GeoGuessr timestamp, which may be missing or malformed in uploaded games.
+fn parse_time(time: Option<&String>) -> Result<DateTime<Utc>, Error> {
+    time.and_then(|time| time.parse().ok())
+        .ok_or_else(|| ErrorBadRequest("Game contains a missing or invalid time!"))
+}
+
+/// Start time of the first round, which is stored as the start time of the game.
+fn get_start_time(first_round_start_time: Option<&String>) -> Result<String, Error> {
+    first_round_start_time
+        .filter(|start_time| start_time.parse::<DateTime<Utc>>().is_ok())
+        .cloned()
+        .ok_or_else(|| ErrorBadRequest("Game contains a missing or invalid start time!"))
+}
+
+fn validate_coordinates(lat: f64, lng: f64) -> Result<(), Error> {
+    if !lat.is_finite() || !lng.is_finite() || !(-90.0..=90.0).contains(&lat) {
+        return Err(ErrorBadRequest(format!("Game contains invalid coordinates {}, {}!", lat, lng)));
+    }
+
+    Ok(())
+}
+
+pub fn validate_duels_game(game: &DuelsGame) -> Result<(), Error> {
+    if game.status.as_str() != "Finished" {
+        return Err(ErrorBadRequest("Game has not finished yet!"));
+    }
+
+    if game.teams.len() != 2 || game.teams.iter().any(|team| team.players.is_empty()) {
+        return Err(ErrorBadRequest("Game does not have two teams with players!"));
+    }
+
+    if game.rounds.is_empty() {
+        return Err(ErrorBadRequest("Game does not have any rounds!"));
+    }
+
+    Ok(())
+}
+
+pub fn validate_solo_game(game: &SoloGame) -> Result<(), Error> {
+    if game.state.as_str() != "finished" {
+        return Err(ErrorBadRequest("Game has not finished yet!"));
+    }
+
+    if game.rounds.is_empty() {
+        return Err(ErrorBadRequest("Game does not have any rounds!"));
+    }
+
+    Ok(())
+}
+
+pub fn validate_battle_royale_game(game: &BattleRoyaleGame) -> Result<(), Error> {
+    if game.status.as_str() != "Finished" {
+        return Err(ErrorBadRequest("Game has not finished yet!"));
+    }
+
+    if game.rounds.is_empty() {
+        return Err(ErrorBadRequest("Game does not have any rounds!"));
+    }
+
+    Ok(())
+}
+
+pub fn transform_duels_game(game: &DuelsGame, metadata: ResolvedMetadata, geocoder: &dyn Geocoder) -> Result<GameData, Error> {
+    validate_duels_game(game)?;
+
+    let mut rounds = Vec::new();
+    let mut guesses = Vec::new();
+    let mut locations = Vec::new();
+    let mut round_results = Vec::new();
+
+    let game_mode = get_game_mode(
+        game.teams[0].players.len(),
+        game.teams[1].players.len(),
+        game.options.is_rated,
+    );
+    let geo_mode = get_geo_mode(&game.options.movement_options);
+
+    let team_ids: Vec<String> = game
+        .teams
+        .iter()
+        .map(|team| match &game_mode {
+            TeamGameMode::Duels | TeamGameMode::DuelsRanked => team.players[0].player_id.clone(),
+            TeamGameMode::TeamDuels | TeamGameMode::TeamDuelsRanked | TeamGameMode::TeamFun => {
+                get_team_id(team.players.iter().map(|player| player.player_id.as_str()).collect())
+            }
+        })
+        .collect();
+
+
+    let duels_game = get_duels_game_model(
+        game,
+        get_start_time(game.rounds[0].start_time.as_ref())?,
+        &game_mode,
+        &geo_mode,
+        team_ids[0].clone(),
+        team_ids[1].clone(),
+        get_team_ratings(&game.teams[0], &game_mode),
+        get_team_ratings(&game.teams[1], &game_mode)
+    );
+
+    let max_error_distance = game.options.map.max_error_distance as f64;
+
+    for (round_number, round) in game
+        .rounds
+        .iter()
+        .filter(|round| round.round_number <= game.current_round_number)
+        .enumerate()
+    {
+        let panorama = &round.panorama;
+        let round_id = Uuid::new_v4().to_string();
+
+        validate_coordinates(panorama.lat, panorama.lng)?;
+
+        locations.push(get_location_model(
+            &panorama.pano_id,
+            panorama.lat,
+            panorama.lng,
+            panorama.heading,
+            panorama.pitch,
+            panorama.zoom,
+            &panorama.country_code,
+            geocoder
+        ));
+
+        let round_starting_date = parse_time(round.start_time.as_ref())?;
+
+        // Guess ids of the round by team, to link the round results to their best guess.
+        let mut round_guess_ids: Vec<(&str, &DuelsGuess, String)> = Vec::new();
+
+        for (team, team_id) in game.teams.iter().zip(team_ids.iter()) {
+            for player in team.players.iter() {
+                let geo_guess_option = player
+                    .guesses
+                    .iter()
+                    .find(|guess| guess.round_number - 1 == round_number as i32);
+
+                if let Some(geo_guess) = geo_guess_option {
+                    validate_coordinates(geo_guess.lat, geo_guess.lng)?;
+                    let guess_date = parse_time(Some(&geo_guess.created))?;
+
+                    let score = geo_guess
+                        .score
+                        .unwrap_or_else(|| get_score(geo_guess.distance, max_error_distance));
+
+                    let geocode = geocoder.geocode(geo_guess.lat, geo_guess.lng);
+
+                    let guess_id = Uuid::new_v4().to_string();
+                    round_guess_ids.push((team_id, geo_guess, guess_id.clone()));
+
+                    let guess = GuessModel {
+                        id: ActiveValue::Set(guess_id),
+                        game_id: ActiveValue::Set(game.game_id.clone()),
+                        round_id: ActiveValue::Set(round_id.clone()),
+                        team_id: ActiveValue::Set(team_id.clone()),
+                        player_id: ActiveValue::Set(Some(player.player_id.clone())),
+                        lat: ActiveValue::Set(geo_guess.lat),
+                        lng: ActiveValue::Set(geo_guess.lng),
+                        score: ActiveValue::Set(score),
+                        time: ActiveValue::Set(Some((guess_date - round_starting_date).num_seconds() as i32)),
+                        date: ActiveValue::Set(guess_date.to_string()),
+                        distance: ActiveValue::Set(geo_guess.distance),
+                        country_code: ActiveValue::Set(geocode.country_code),
+                        subdivision_code: ActiveValue::Set(geocode.subdivision_code),
+                        round_country_code: ActiveValue::Set(round.panorama.country_code.clone().to_ascii_uppercase()),
+                        is_teams_best: ActiveValue::Set(geo_guess.is_teams_best_guess_on_round),
+                        border_distance: ActiveValue::Set(geocode.border_distance),
+                        is_score_derived: ActiveValue::Set(Some(geo_guess.score.is_none()))
+                    };
+
+                    guesses.push(guess);
+                }
+            }
+        }
+
+        for (team, team_id) in game.teams.iter().zip(team_ids.iter()) {
+            let round_result_option = team
+                .round_results
+                .iter()
+                .find(|round_result| round_result.round_number == round.round_number);
+
+            if let Some(round_result) = round_result_option {
+                let best_guess_id = round_result.best_guess.as_ref().and_then(|best_guess| {
+                    round_guess_ids
+                        .iter()
+                        .find(|(guess_team_id, guess, _)| {
+                            guess_team_id == team_id
+                                && guess.created == best_guess.created
+                                && guess.lat == best_guess.lat
+                                && guess.lng == best_guess.lng
+                        })
+                        .map(|(_, _, guess_id)| guess_id.clone())
+                });
+
+                round_results.push(RoundResultModel {
+                    id: ActiveValue::Set(Uuid::new_v4().to_string()),
+                    game_id: ActiveValue::Set(game.game_id.clone()),
+                    round_id: ActiveValue::Set(round_id.clone()),
+                    team_id: ActiveValue::Set(team_id.clone()),
+                    round_number: ActiveValue::Set(round_number as i32),
+                    score: ActiveValue::Set(round_result.score),
+                    health_before: ActiveValue::Set(round_result.health_before),
+                    health_after: ActiveValue::Set(round_result.health_after),
+                    best_guess_id: ActiveValue::Set(best_guess_id)
+                });
+            }
+        }
+
+        let round = DuelsRoundModel {
+            id: ActiveValue::Set(round_id),
+            game_id: ActiveValue::Set(game.game_id.clone()),
+            location_id: ActiveValue::Set(panorama.pano_id.clone()),
+            round_country_code: ActiveValue::Set(round.panorama.country_code.clone().to_ascii_uppercase()),
+            round_number: ActiveValue::Set(round_number as i32),
+            damage_multiplier: ActiveValue::Set(round.damage_multiplier),
+            multiplier: ActiveValue::Set(round.multiplier),
+            is_healing_round: ActiveValue::Set(round.is_healing_round)
+        };
+
+        rounds.push(round);
+    }
+
+    let map = MapModel {
+        id: ActiveValue::Set(game.options.map.slug.clone()),
+        name: ActiveValue::Set(game.options.map.name.clone()),
+        lat1: ActiveValue::Set(game.map_bounds.min.lat),
+        lng1: ActiveValue::Set(game.map_bounds.min.lng),
+        lat2: ActiveValue::Set(game.map_bounds.max.lat),
+        lng2: ActiveValue::Set(game.map_bounds.max.lng),
+        max_distance: ActiveValue::Set(game.options.map.max_error_distance)
+    };
+
+    let game_data = GameData {
+        duels_game,
+        rounds,
+        guesses,
+        locations,
+        round_results,
+        players: metadata.players,
+        comp_teams: metadata.comp_teams,
+        fun_teams: metadata.fun_teams,
+        map
+    };
+
+    Ok(game_data)
+}
+
+/// Classic games carry a default `streak_type` as well, so only games in streak mode count.
+fn get_streak_type(game: &SoloGame) -> Option<StreakType> {
+    if game.mode.as_str() != "streak" {
+        return None;
+    }
+
+    StreakType::from_str(&game.streak_type).ok()
+}
+
+/// Location code of the first round that was not guessed correctly, `None` if the streak did not end.
+fn get_streak_end_location_code(game: &SoloGame) -> Option<String> {
+    game.rounds
+        .iter()
+        .zip(game.player.guesses.iter())
+        .find(|(round, guess)| {
+            guess
+                .streak_location_code
+                .as_ref()
+                .is_none_or(|location_code| !location_code.eq_ignore_ascii_case(&round.streak_location_code))
+        })
+        .map(|(round, _)| round.streak_location_code.to_ascii_uppercase())
+}
+
+/// `max_error_distance` is the map's real max error distance if it is already known,
+/// solo games only contain the map bounds.
+pub fn transform_solo_game(
+    game: &SoloGame,
+    player: Option<PlayerModel>,
+    max_error_distance: Option<i32>,
+    geocoder: &dyn Geocoder
+) -> Result<SoloGameData, Error> {
+    validate_solo_game(game)?;
+
+    let geo_mode = get_geo_mode(&MovementOption {
+        forbid_moving: game.forbid_moving,
+        forbid_zooming: game.forbid_zooming,
+        forbid_rotating: game.forbid_rotating
+    });
+
+    let streak_type = get_streak_type(game);
+
+    let mut rounds = Vec::with_capacity(game.round as usize);
+    let mut guesses = Vec::with_capacity(game.round as usize);
+    let mut locations = Vec::with_capacity(game.round as usize);
+
+    for (round_number, (round, solo_guess)) in game.rounds.iter().zip(game.player.guesses.iter()).enumerate() {
+        let round_id = Uuid::new_v4().to_string();
+
+        // In US state streaks the location code is the state, not the country.
+        let country_code = match streak_type {
+            Some(StreakType::UsStateStreak) => "us",
+            _ => &round.streak_location_code
+        };
+
+        validate_coordinates(round.lat, round.lng)?;
+
+        let location = get_location_model(
+            &round.pano_id,
+            round.lat,
+            round.lng,
+            round.heading,
+            round.pitch,
+            round.zoom,
+            country_code,
+            geocoder
+        );
+
+        locations.push(location);
+
+        let round_model = SoloRoundModel {
+            id: ActiveValue::Set(round_id.clone()),
+            game_id: ActiveValue::Set(game.token.clone()),
+            location_id: ActiveValue::Set(round.pano_id.clone()),
+            round_number: ActiveValue::Set(round_number as i32),
+        };
+
+        rounds.push(round_model);
+
+        validate_coordinates(solo_guess.lat, solo_guess.lng)?;
+        let geocode = geocoder.geocode(solo_guess.lat, solo_guess.lng);
+
+        let round_start_time = parse_time(round.start_time.as_ref())?;
+
+        let guess = GuessModel {
+            id: ActiveValue::Set(Uuid::new_v4().to_string()),
+            game_id: ActiveValue::Set(game.token.clone()),
+            round_id: ActiveValue::Set(round_id),
+            team_id: ActiveValue::Set(game.player.id.clone()),
+            player_id: ActiveValue::Set(Some(game.player.id.clone())),
+            lat: ActiveValue::Set(solo_guess.lat),
+            lng: ActiveValue::Set(solo_guess.lng),
+            score: ActiveValue::Set(solo_guess.round_score_in_points),
+            time: ActiveValue::Set(Some(solo_guess.time)),
+            date: ActiveValue::Set((round_start_time + TimeDelta::seconds(solo_guess.time as i64)).to_string()),
+            distance: ActiveValue::Set(solo_guess.distance_in_meters),
+            country_code: ActiveValue::Set(geocode.country_code),
+            subdivision_code: ActiveValue::Set(geocode.subdivision_code),
+            round_country_code: ActiveValue::Set(country_code.to_uppercase()),
+            is_teams_best: ActiveValue::Set(true),
+            border_distance: ActiveValue::Set(geocode.border_distance),
+            is_score_derived: ActiveValue::Set(Some(false))
+        };
+
+        guesses.push(guess);
+    }
+
+    let solo_game = SoloGameModel {
+        id: ActiveValue::Set(game.token.clone()),
+        player_id: ActiveValue::Set(game.player.id.clone()),
+        geo_mode: ActiveValue::Set(geo_mode.to_string()),
+        start_time: ActiveValue::Set(get_start_time(game.rounds[0].start_time.as_ref())?),
+        map_id: ActiveValue::Set(game.map.clone()),
+        streak_type: ActiveValue::Set(streak_type.as_ref().map(|streak_type| streak_type.to_string())),
+        streak_length: ActiveValue::Set(streak_type.as_ref().map(|_| game.player.total_streak)),
+        streak_end_location_code: ActiveValue::Set(streak_type.as_ref().and_then(|_| get_streak_end_location_code(game))),
+    };
+
+    let max_distance = max_error_distance.unwrap_or_else(|| {
+        get_bounds_diagonal(game.bounds.min.lat, game.bounds.min.lng, game.bounds.max.lat, game.bounds.max.lng) as i32
+    });
+
+    let map = MapModel {
+        id: ActiveValue::Set(game.map.clone()),
+        name: ActiveValue::Set(game.map_name.clone()),
+        lat1: ActiveValue::Set(game.bounds.min.lat),
+        lng1: ActiveValue::Set(game.bounds.min.lng),
+        lat2: ActiveValue::Set(game.bounds.max.lat),
+        lng2: ActiveValue::Set(game.bounds.max.lng),
+        max_distance: ActiveValue::Set(max_distance),
+    };
+
+    let solo_game_data = SoloGameData {
+        solo_game,
+        rounds,
+        guesses,
+        locations,
+        player,
+        map
+    };
+
+    Ok(solo_game_data)
+}
+
+/// Whether a countries game guess picked the country of its round.
+fn is_correct_country_guess(guess: &BattleRoyaleGuess, rounds: &[BattleRoyaleRound]) -> bool {
+    guess.is_correct.unwrap_or_else(|| {
+        rounds
+            .iter()
+            .find(|round| round.round_number == guess.round_number)
+            .zip(guess.country_code.as_ref())
+            .is_some_and(|(round, country_code)| round.country_code.eq_ignore_ascii_case(country_code))
+    })
+}
+
+/// Final position of a player, players that were knocked out in the same round share it.
+fn get_battle_royale_position(player: &BattleRoyalePlayer, players: &[BattleRoyalePlayer]) -> i32 {
+    let knocked_out_at_round = player.knocked_out_at_round.unwrap_or(i32::MAX);
+
+    1 + players
+        .iter()
+        .filter(|other| other.knocked_out_at_round.unwrap_or(i32::MAX) > knocked_out_at_round)
+        .count() as i32
+}
+
+pub fn transform_battle_royale_game(game: &BattleRoyaleGame, geocoder: &dyn Geocoder) -> Result<BattleRoyaleGameData, Error> {
+    validate_battle_royale_game(game)?;
+
+    let game_mode = if game.is_distance_game {
+        BattleRoyaleMode::BattleRoyaleDistance
+    } else {
+        BattleRoyaleMode::BattleRoyaleCountries
+    };
+
+    let mut rounds = Vec::new();
+    let mut guesses = Vec::new();
+    let mut locations = Vec::new();
+
+    for (round_number, round) in game
+        .rounds
+        .iter()
+        .filter(|round| round.round_number <= game.current_round_number)
+        .enumerate()
+    {
+        let round_id = Uuid::new_v4().to_string();
+        let round_country_code = round.country_code.to_ascii_uppercase();
+        let round_starting_date = parse_time(round.start_time.as_ref())?;
+        let round_location = geoutils::Location::new(round.lat, round.lng);
+
+        validate_coordinates(round.lat, round.lng)?;
+
+        locations.push(get_location_model(
+            &round.pano_id,
+            round.lat,
+            round.lng,
+            round.heading,
+            round.pitch,
+            round.zoom,
+            &round.country_code,
+            geocoder
+        ));
+
+        let round_guesses: Vec<(&BattleRoyalePlayer, usize)> = game
+            .players
+            .iter()
+            .flat_map(|player| {
+                player
+                    .guesses
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, guess)| guess.round_number == round.round_number)
+                    .map(move |(index, _)| (player, index))
+            })
+            .collect();
+
+        let distances: Vec<f64> = round_guesses
+            .iter()
+            .map(|(player, index)| {
+                let guess = &player.guesses[*index];
+
+                guess.distance.unwrap_or_else(|| {
+                    round_location.haversine_distance_to(&geoutils::Location::new(guess.lat, guess.lng)).meters()
+                })
+            })
+            .collect();
+
+        for ((player, index), distance) in round_guesses.iter().zip(distances.iter()) {
+            let geo_guess = &player.guesses[*index];
+            let guess_date = parse_time(Some(&geo_guess.created))?;
+
+            validate_coordinates(geo_guess.lat, geo_guess.lng)?;
+            let geocode = geocoder.geocode(geo_guess.lat, geo_guess.lng);
+
+            let country_code = match &geo_guess.country_code {
+                Some(country_code) => Some(country_code.to_ascii_uppercase()),
+                None => geocode.country_code
+            };
+
+            let is_correct = geo_guess
+                .is_correct
+                .unwrap_or_else(|| country_code.as_ref().is_some_and(|country_code| country_code == &round_country_code));
+
+            // Every wrong guess costs a life, so the lives after a guess are the final lives
+            // plus the lives lost on the player's later guesses.
+            let lives_after = (game_mode == BattleRoyaleMode::BattleRoyaleCountries).then(|| {
+                player.lives + player.guesses[index + 1..]
+                    .iter()
+                    .filter(|guess| !is_correct_country_guess(guess, &game.rounds))
+                    .count() as i32
+            });
+
+            let position = (game_mode == BattleRoyaleMode::BattleRoyaleDistance)
+                .then(|| 1 + distances.iter().filter(|other| *other < distance).count() as i32);
+
+            guesses.push(BrGuessModel {
+                id: ActiveValue::Set(Uuid::new_v4().to_string()),
+                game_id: ActiveValue::Set(game.game_id.clone()),
+                round_id: ActiveValue::Set(round_id.clone()),
+                player_id: ActiveValue::Set(player.player_id.clone()),
+                lat: ActiveValue::Set(geo_guess.lat),
+                lng: ActiveValue::Set(geo_guess.lng),
+                country_code: ActiveValue::Set(country_code),
+                subdivision_code: ActiveValue::Set(geocode.subdivision_code),
+                round_country_code: ActiveValue::Set(round_country_code.clone()),
+                distance: ActiveValue::Set(*distance),
+                is_correct: ActiveValue::Set(is_correct),
+                lives_after: ActiveValue::Set(lives_after),
+                position: ActiveValue::Set(position),
+                time: ActiveValue::Set(Some((guess_date - round_starting_date).num_seconds() as i32)),
+                date: ActiveValue::Set(guess_date.to_string())
+            });
+        }
+
+        rounds.push(BrRoundModel {
+            id: ActiveValue::Set(round_id),
+            game_id: ActiveValue::Set(game.game_id.clone()),
+            location_id: ActiveValue::Set(round.pano_id.clone()),
+            round_country_code: ActiveValue::Set(round_country_code),
+            round_number: ActiveValue::Set(round_number as i32)
+        });
+    }
+
+    let players = game
+        .players
+        .iter()
+        .map(|player| BrPlayerModel {
+            id: ActiveValue::Set(Uuid::new_v4().to_string()),
+            game_id: ActiveValue::Set(game.game_id.clone()),
+            player_id: ActiveValue::Set(player.player_id.clone()),
+            nick: ActiveValue::Set(player.nick.clone()),
+            lives: ActiveValue::Set(player.lives),
+            knocked_out_at_round: ActiveValue::Set(player.knocked_out_at_round),
+            position: ActiveValue::Set(get_battle_royale_position(player, &game.players))
+        })
+        .collect();
+
+    let br_game = BrGameModel {
+        id: ActiveValue::Set(game.game_id.clone()),
+        game_mode: ActiveValue::Set(game_mode.to_string()),
+        geo_mode: ActiveValue::Set(get_geo_mode(&game.movement_options).to_string()),
+        start_time: ActiveValue::Set(get_start_time(game.rounds[0].start_time.as_ref())?),
+        map_id: ActiveValue::Set(game.map_slug.clone()),
+        round_count: ActiveValue::Set(rounds.len() as i32)
+    };
+
+    let battle_royale_game_data = BattleRoyaleGameData {
+        br_game,
+        players,
+        rounds,
+        guesses,
+        locations
+    };
+
+    Ok(battle_royale_game_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::requests::geocoder::GeocodeResult;
+    use serde_json::Value;
+
+    const DUELS_GAME: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/duels_game.json"));
+    const SOLO_GAME: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/solo_game.json"));
+    const BATTLE_ROYALE_GAME: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/battle_royale_game.json"));
+
+    /// Puts every guess in Germany, 1 km from the border.
+    struct StubGeocoder;
+
+    impl Geocoder for StubGeocoder {
+        fn geocode(&self, lat: f64, lng: f64) -> GeocodeResult {
+            GeocodeResult {
+                country_code: Some(String::from("DE")),
+                subdivision_code: self.subdivision_code(lat, lng),
+                border_distance: Some(1000.0)
+            }
+        }
+
+        fn subdivision_code(&self, _lat: f64, _lng: f64) -> Option<String> {
+            Some(String::from("DE-BY"))
+        }
+
+        fn dataset_version(&self) -> &str {
+            "stub"
+        }
+    }
+
+    fn fixture(json: &str) -> Value {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn duels_game(value: Value) -> DuelsGame {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn solo_game(value: Value) -> SoloGame {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn transform_duels(value: Value) -> Result<GameData, Error> {
+        transform_duels_game(&duels_game(value), ResolvedMetadata::default(), &StubGeocoder)
+    }
+
+    fn transform_solo(value: Value) -> Result<SoloGameData, Error> {
+        transform_solo_game(&solo_game(value), None, Some(14916862), &StubGeocoder)
+    }
+
+    #[test]
+    fn transforms_duels_game() {
+        let game_data = transform_duels(fixture(DUELS_GAME)).unwrap();
+
+        let duels_game = &game_data.duels_game;
+        assert_eq!(duels_game.team_id1.as_ref(), "player-a");
+        assert_eq!(duels_game.team_id2.as_ref(), "player-b");
+        assert_eq!(duels_game.team_game_mode.as_ref(), "DuelsRanked");
+        assert_eq!(duels_game.geo_mode.as_ref(), "NMPZ");
+        assert_eq!(duels_game.start_time.as_ref(), "2025-04-20T12:00:00.000Z");
+        assert_eq!(*duels_game.rating_before_team1.as_ref(), Some(1200));
+        assert_eq!(*duels_game.rating_after_team2.as_ref(), Some(1190));
+
+        // The third round was never played.
+        assert_eq!(game_data.rounds.len(), 2);
+        assert_eq!(game_data.locations.len(), 2);
+        assert_eq!(game_data.rounds[0].round_country_code.as_ref(), "AT");
+        assert_eq!(*game_data.rounds[1].round_number.as_ref(), 1);
+        assert_eq!(game_data.guesses.len(), 4);
+        assert_eq!(game_data.round_results.len(), 4);
+        assert_eq!(*game_data.map.max_distance.as_ref(), 14916862);
+    }
+
+    #[test]
+    fn derives_missing_duels_scores() {
+        let game_data = transform_duels(fixture(DUELS_GAME)).unwrap();
+
+        let reported = &game_data.guesses[0];
+        assert_eq!(*reported.score.as_ref(), 4522);
+        assert_eq!(*reported.is_score_derived.as_ref(), Some(false));
+        assert_eq!(*reported.time.as_ref(), Some(20));
+        assert_eq!(reported.country_code.as_ref().as_deref(), Some("DE"));
+        assert_eq!(reported.round_country_code.as_ref(), "AT");
+
+        let derived = &game_data.guesses[1];
+        assert_eq!(derived.team_id.as_ref(), "player-b");
+        assert_eq!(*derived.score.as_ref(), 2237);
+        assert_eq!(*derived.is_score_derived.as_ref(), Some(true));
+        assert_eq!(*derived.time.as_ref(), Some(35));
+    }
+
+    #[test]
+    fn links_duels_round_results_to_best_guesses() {
+        let game_data = transform_duels(fixture(DUELS_GAME)).unwrap();
+
+        let best_guess_id = game_data.round_results[0].best_guess_id.as_ref().clone();
+        assert_eq!(best_guess_id.as_ref(), Some(game_data.guesses[0].id.as_ref()));
+        assert_eq!(*game_data.round_results[1].best_guess_id.as_ref(), None);
+        assert_eq!(*game_data.round_results[2].health_after.as_ref(), 4100);
+    }
+
+    #[test]
+    fn rejects_invalid_duels_games() {
+        let mut unfinished = fixture(DUELS_GAME);
+        unfinished["status"] = Value::from("Ongoing");
+        assert!(transform_duels(unfinished).is_err());
+
+        let mut one_team = fixture(DUELS_GAME);
+        one_team["teams"].as_array_mut().unwrap().pop();
+        assert!(transform_duels(one_team).is_err());
+
+        let mut no_rounds = fixture(DUELS_GAME);
+        no_rounds["rounds"] = Value::Array(Vec::new());
+        assert!(transform_duels(no_rounds).is_err());
+
+        let mut missing_start_time = fixture(DUELS_GAME);
+        missing_start_time["rounds"][0]["startTime"] = Value::Null;
+        assert!(transform_duels(missing_start_time).is_err());
+
+        let mut invalid_guess_time = fixture(DUELS_GAME);
+        invalid_guess_time["teams"][0]["players"][0]["guesses"][0]["created"] = Value::from("yesterday");
+        assert!(transform_duels(invalid_guess_time).is_err());
+
+        let mut invalid_coordinates = fixture(DUELS_GAME);
+        invalid_coordinates["rounds"][1]["panorama"]["lat"] = Value::from(91.0);
+        assert!(transform_duels(invalid_coordinates).is_err());
+    }
+
+    #[test]
+    fn transforms_classic_solo_game() {
+        let solo_game_data = transform_solo(fixture(SOLO_GAME)).unwrap();
+
+        let solo_game = &solo_game_data.solo_game;
+        assert_eq!(solo_game.geo_mode.as_ref(), "NoPanning");
+        assert_eq!(solo_game.start_time.as_ref(), "2025-04-20T13:00:00.000Z");
+
+        assert_eq!(solo_game_data.rounds.len(), 2);
+        assert_eq!(solo_game_data.guesses.len(), 2);
+
+        let guess = &solo_game_data.guesses[1];
+        assert_eq!(*guess.score.as_ref(), 2558);
+        assert_eq!(*guess.time.as_ref(), Some(45));
+        assert_eq!(guess.date.as_ref(), "2025-04-20 13:02:15 UTC");
+        assert_eq!(guess.round_country_code.as_ref(), "US");
+        assert_eq!(*solo_game_data.map.max_distance.as_ref(), 14916862);
+    }
+
+    fn streak_game(streak_type: &str, round_codes: [&str; 2], guess_codes: [&str; 2]) -> Value {
+        let mut value = fixture(SOLO_GAME);
+        value["mode"] = Value::from("streak");
+        value["streakType"] = Value::from(streak_type);
+
+        for (index, (round_code, guess_code)) in round_codes.iter().zip(guess_codes.iter()).enumerate() {
+            value["rounds"][index]["streakLocationCode"] = Value::from(*round_code);
+            value["player"]["guesses"][index]["streakLocationCode"] = Value::from(*guess_code);
+        }
+
+        value
+    }
+
+    #[test]
+    fn ignores_default_streak_type_of_classic_games() {
+        let solo_game_data = transform_solo(fixture(SOLO_GAME)).unwrap();
+
+        let solo_game = &solo_game_data.solo_game;
+        assert_eq!(*solo_game.streak_type.as_ref(), None);
+        assert_eq!(*solo_game.streak_length.as_ref(), None);
+        assert_eq!(*solo_game.streak_end_location_code.as_ref(), None);
+        assert_eq!(solo_game_data.guesses.len(), 2);
+    }
+
+    #[test]
+    fn transforms_country_streak() {
+        let solo_game_data = transform_solo(streak_game("CountryStreak", ["jp", "us"], ["jp", "ca"])).unwrap();
+
+        let solo_game = &solo_game_data.solo_game;
+        assert_eq!(solo_game.streak_type.as_ref().as_deref(), Some("CountryStreak"));
+        assert_eq!(*solo_game.streak_length.as_ref(), Some(1));
+        assert_eq!(solo_game.streak_end_location_code.as_ref().as_deref(), Some("US"));
+
+        assert_eq!(solo_game_data.guesses.len(), 2);
+        assert_eq!(solo_game_data.guesses[0].round_country_code.as_ref(), "JP");
+        assert_eq!(solo_game_data.guesses[1].round_country_code.as_ref(), "US");
+        assert_eq!(solo_game_data.rounds.len(), 2);
+        assert_eq!(solo_game_data.locations[0].country_code.as_ref(), "JP");
+        assert_eq!(solo_game_data.locations[1].country_code.as_ref(), "US");
+    }
+
+    #[test]
+    fn transforms_us_state_streak() {
+        let solo_game_data = transform_solo(streak_game("UsStateStreak", ["ny", "ca"], ["ny", "nv"])).unwrap();
+
+        let solo_game = &solo_game_data.solo_game;
+        assert_eq!(solo_game.streak_type.as_ref().as_deref(), Some("UsStateStreak"));
+        assert_eq!(solo_game.streak_end_location_code.as_ref().as_deref(), Some("CA"));
+
+        // The location code is the state, the locations are still in the US.
+        assert!(solo_game_data.locations.iter().all(|location| location.country_code.as_ref() == "US"));
+        assert!(solo_game_data.guesses.iter().all(|guess| guess.round_country_code.as_ref() == "US"));
+    }
+
+    #[test]
+    fn unbroken_streak_has_no_end_location() {
+        let solo_game_data = transform_solo(streak_game("CountryStreak", ["jp", "us"], ["JP", "us"])).unwrap();
+
+        assert_eq!(*solo_game_data.solo_game.streak_end_location_code.as_ref(), None);
+    }
+
+    #[test]
+    fn rejects_invalid_solo_games() {
+        let mut unfinished = fixture(SOLO_GAME);
+        unfinished["state"] = Value::from("started");
+        assert!(transform_solo(unfinished).is_err());
+
+        let mut no_rounds = fixture(SOLO_GAME);
+        no_rounds["rounds"] = Value::Array(Vec::new());
+        assert!(transform_solo(no_rounds).is_err());
+
+        let mut missing_start_time = fixture(SOLO_GAME);
+        missing_start_time["rounds"][1]["startTime"] = Value::Null;
+        assert!(transform_solo(missing_start_time).is_err());
+    }
+
+    fn transform_battle_royale(value: Value) -> Result<BattleRoyaleGameData, Error> {
+        let game: BattleRoyaleGame = serde_json::from_value(value).unwrap();
+        transform_battle_royale_game(&game, &StubGeocoder)
+    }
+
+    fn player_guesses<'a>(game_data: &'a BattleRoyaleGameData, player_id: &str) -> Vec<&'a BrGuessModel> {
+        game_data
+            .guesses
+            .iter()
+            .filter(|guess| guess.player_id.as_ref() == player_id)
+            .collect()
+    }
+
+    fn player_position(game_data: &BattleRoyaleGameData, player_id: &str) -> i32 {
+        *game_data
+            .players
+            .iter()
+            .find(|player| player.player_id.as_ref() == player_id)
+            .unwrap()
+            .position
+            .as_ref()
+    }
+
+    fn distance_game() -> Value {
+        let guess = |lat: f64, lng: f64, distance: Option<f64>| serde_json::json!({
+            "roundNumber": 1,
+            "lat": lat,
+            "lng": lng,
+            "distance": distance,
+            "created": "2025-04-20T15:00:10.000Z"
+        });
+        let player = |player_id: &str, guess: Value| serde_json::json!({
+            "playerId": player_id,
+            "nick": player_id,
+            "lives": 1,
+            "guesses": [guess]
+        });
+
+        serde_json::json!({
+            "gameId": "br-distance-fixture",
+            "status": "Finished",
+            "isDistanceGame": true,
+            "currentRoundNumber": 1,
+            "movementOptions": { "forbidMoving": true, "forbidZooming": false, "forbidRotating": false },
+            "rounds": [
+                { "roundNumber": 1, "panoId": "br-pano-5", "lat": 0.0, "lng": 0.0, "countryCode": "de", "startTime": "2025-04-20T15:00:00.000Z" }
+            ],
+            "players": [
+                player("far", guess(4.5, 0.0, Some(500000.0))),
+                player("close", guess(0.9, 0.0, Some(100000.0))),
+                player("tied", guess(0.0, 0.9, Some(100000.0))),
+                player("unreported", guess(0.0, 1.0, None))
+            ]
+        })
+    }
+
+    #[test]
+    fn transforms_battle_royale_countries_game() {
+        let game_data = transform_battle_royale(fixture(BATTLE_ROYALE_GAME)).unwrap();
+
+        let br_game = &game_data.br_game;
+        assert_eq!(br_game.game_mode.as_ref(), "BattleRoyaleCountries");
+        assert_eq!(br_game.geo_mode.as_ref(), "Moving");
+        assert_eq!(br_game.start_time.as_ref(), "2025-04-20T14:00:00.000Z");
+
+        // The fourth round was never played.
+        assert_eq!(*br_game.round_count.as_ref(), 3);
+        assert_eq!(game_data.rounds.len(), 3);
+        assert_eq!(game_data.guesses.len(), 15);
+        assert!(game_data.guesses.iter().all(|guess| guess.position.as_ref().is_none()));
+
+        let guesses = player_guesses(&game_data, "player-1");
+        let is_correct: Vec<bool> = guesses.iter().map(|guess| *guess.is_correct.as_ref()).collect();
+        assert_eq!(is_correct, [false, true, true, true]);
+        assert_eq!(guesses[0].country_code.as_ref().as_deref(), Some("ES"));
+        assert_eq!(guesses[0].round_country_code.as_ref(), "FR");
+        assert_eq!(*guesses[0].time.as_ref(), Some(5));
+    }
+
+    #[test]
+    fn reconstructs_battle_royale_lives() {
+        let game_data = transform_battle_royale(fixture(BATTLE_ROYALE_GAME)).unwrap();
+
+        let lives_after = |player_id: &str| -> Vec<Option<i32>> {
+            player_guesses(&game_data, player_id)
+                .iter()
+                .map(|guess| *guess.lives_after.as_ref())
+                .collect()
+        };
+
+        assert_eq!(lives_after("player-1"), [Some(2), Some(2), Some(2), Some(2)]);
+        assert_eq!(lives_after("player-2"), [Some(2), Some(2), Some(1), Some(0)]);
+        assert_eq!(lives_after("player-3"), [Some(2), Some(1), Some(0)]);
+        assert_eq!(lives_after("player-4"), [Some(3), Some(2), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn ranks_battle_royale_players_by_knock_out_round() {
+        let game_data = transform_battle_royale(fixture(BATTLE_ROYALE_GAME)).unwrap();
+
+        assert_eq!(player_position(&game_data, "player-1"), 1);
+        // Players knocked out in the same round share their position.
+        assert_eq!(player_position(&game_data, "player-2"), 2);
+        assert_eq!(player_position(&game_data, "player-4"), 2);
+        assert_eq!(player_position(&game_data, "player-3"), 4);
+    }
+
+    #[test]
+    fn ranks_battle_royale_distance_guesses() {
+        let game_data = transform_battle_royale(distance_game()).unwrap();
+
+        assert_eq!(game_data.br_game.game_mode.as_ref(), "BattleRoyaleDistance");
+
+        let position = |player_id: &str| *player_guesses(&game_data, player_id)[0].position.as_ref();
+
+        assert_eq!(position("close"), Some(1));
+        assert_eq!(position("tied"), Some(1));
+        assert_eq!(position("unreported"), Some(3));
+        assert_eq!(position("far"), Some(4));
+
+        // Missing distances are computed from the coordinates, about 111 km per degree.
+        let distance = *player_guesses(&game_data, "unreported")[0].distance.as_ref();
+        assert!((distance - 111_195.0).abs() < 100.0);
+
+        assert!(game_data.guesses.iter().all(|guess| guess.lives_after.as_ref().is_none()));
+        assert!(game_data.guesses.iter().all(|guess| *guess.is_correct.as_ref()));
+    }
+
+    #[test]
+    fn rejects_invalid_battle_royale_games() {
+        let mut unfinished = fixture(BATTLE_ROYALE_GAME);
+        unfinished["status"] = Value::from("Ongoing");
+        assert!(transform_battle_royale(unfinished).is_err());
+
+        let mut missing_start_time = fixture(BATTLE_ROYALE_GAME);
+        missing_start_time["rounds"][1]["startTime"] = Value::Null;
+        assert!(transform_battle_royale(missing_start_time).is_err());
+
+        let mut invalid_guess_time = fixture(BATTLE_ROYALE_GAME);
+        invalid_guess_time["players"][0]["guesses"][0]["created"] = Value::from("");
+        assert!(transform_battle_royale(invalid_guess_time).is_err());
+    }
+}